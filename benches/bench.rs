@@ -1,4 +1,5 @@
 #![feature(test)]
+#![allow(clippy::excessive_precision, clippy::approx_constant)]
 
 extern crate test;
 extern crate iq_osc;
@@ -6,6 +7,7 @@ extern crate iq_osc;
 use std::f32::consts::PI as PI32;
 use std::f64::consts::PI as PI64;
 use iq_osc::IQOsc;
+use iq_osc::bank::OscBank;
 
 #[bench]
 fn bench_trig32(b: &mut test::Bencher) {
@@ -764,3 +766,30 @@ fn bench_osc64(b: &mut test::Bencher) {
         }
     });
 }
+
+#[bench]
+fn bench_array32(b: &mut test::Bencher) {
+    let mut oscs: Vec<IQOsc<f32>> = (0..256).map(|k| IQOsc::new(0.0, k as f32 * 0.01))
+        .collect();
+
+    b.iter(|| {
+        for _ in 0..256 {
+            for osc in oscs.iter_mut() {
+                test::black_box(osc.next());
+            }
+        }
+    });
+}
+
+#[bench]
+fn bench_bank32(b: &mut test::Bencher) {
+    let steps: Vec<f32> = (0..256).map(|k| k as f32 * 0.01).collect();
+    let mut bank = OscBank::new(&[0.0; 256], &steps);
+
+    b.iter(|| {
+        for _ in 0..256 {
+            bank.advance();
+            test::black_box(bank.sin());
+        }
+    });
+}
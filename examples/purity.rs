@@ -0,0 +1,50 @@
+//! Print the spectral purity of an `IQOsc`.
+//!
+//! Usage: `cargo run --example purity -- FREQ [SAMPLES] [f32|f64]`, where FREQ is the
+//! tone frequency in cycles per sample.
+
+extern crate iq_osc;
+
+use std::env;
+use std::f64::consts::PI;
+use std::process;
+
+use iq_osc::IQOsc;
+use iq_osc::spectrum::{analyze, Window};
+
+/// Print the usage and exit with an error.
+fn usage() -> ! {
+    eprintln!("usage: purity FREQ [SAMPLES] [f32|f64]");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let freq: f64 = match args.first().and_then(|s| s.parse().ok()) {
+        Some(f) => f,
+        None => usage(),
+    };
+
+    let samples: usize = match args.get(1).map(|s| s.parse()) {
+        None => 1 << 16,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => usage(),
+    };
+
+    let step = 2.0 * PI * freq;
+
+    let report = match args.get(2).map(|s| &s[..]) {
+        Some("f32") => {
+            let mut osc = IQOsc::new(0.0, step as f32);
+            analyze(samples, Window::BlackmanHarris, 8, || osc.next())
+        },
+        None | Some("f64") => {
+            let mut osc = IQOsc::new(0.0, step);
+            analyze(samples, Window::BlackmanHarris, 8, || osc.next())
+        },
+        Some(_) => usage(),
+    };
+
+    println!("{}", report);
+}
@@ -0,0 +1,360 @@
+//! Drift and accuracy measurement against a high-precision reference.
+//!
+//! A `DriftMeter` runs an `IQOsc<DoubleDouble>` in lockstep with the samples of any
+//! oscillator under test and accumulates error statistics, which are summarized in a
+//! `DriftReport`. All per-sample differences are computed in double-double precision,
+//! so the errors of `f64` oscillators are measured without being masked by the
+//! rounding of the reference.
+
+use core::fmt;
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use dd::DoubleDouble;
+use IQOsc;
+
+/// Summary of an oscillator's error over a run, compared to an exact reference.
+///
+/// All angles are in radians and all amplitudes are relative to the unit circle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DriftReport {
+    /// Number of samples compared.
+    pub samples: u64,
+    /// Maximum of ||z| - 1| for each output sample z = cos + j sin.
+    pub max_amplitude_err: f64,
+    /// RMS of |z| - 1.
+    pub rms_amplitude_err: f64,
+    /// Maximum absolute difference between the phase of z and the reference phase.
+    pub max_phase_err: f64,
+    /// RMS of the phase difference.
+    pub rms_phase_err: f64,
+    /// Signed phase difference at the last sample, which shows the direction of any
+    /// accumulated drift.
+    pub final_phase_err: f64,
+    /// Deviation from 90° of the angle between the cos (I) and sin (Q) channels.
+    ///
+    /// This is estimated by a least-squares fit of each channel to the reference, so
+    /// the run should cover a reasonable fraction of a cycle.
+    pub quadrature_err: f64,
+    /// Ratio of the Q channel gain to the I channel gain, estimated in the same way
+    /// as `quadrature_err`.
+    pub gain_imbalance: f64,
+    /// Maximum error of each component in units in the last place of the reference
+    /// value rounded to the oscillator's type.
+    ///
+    /// Note that ULPs are relative, so components near a zero crossing may show large
+    /// ULP errors even when their absolute error is small.
+    pub max_ulps: f64,
+    /// Mean component error in ULPs.
+    pub mean_ulps: f64,
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "samples:        {}", self.samples)?;
+        writeln!(f, "amplitude err:  max {:.3e}, rms {:.3e}",
+                 self.max_amplitude_err, self.rms_amplitude_err)?;
+        writeln!(f, "phase err:      max {:.3e}, rms {:.3e}, final {:.3e}",
+                 self.max_phase_err, self.rms_phase_err, self.final_phase_err)?;
+        writeln!(f, "quadrature err: {:.3e} (gain imbalance {:.6})",
+                 self.quadrature_err, self.gain_imbalance)?;
+        write!(f, "ulps:           max {:.3}, mean {:.3}", self.max_ulps, self.mean_ulps)
+    }
+}
+
+/// Accumulates error statistics of oscillator samples against a reference oscillator.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DriftMeter {
+    /// Reference oscillator with the same initial phase and phase step.
+    reference: IQOsc<DoubleDouble>,
+    /// Number of samples compared so far.
+    samples: u64,
+    /// Running maximum amplitude error.
+    max_amp: f64,
+    /// Running sum of squared amplitude errors.
+    sum_amp: f64,
+    /// Running maximum phase error.
+    max_phase: f64,
+    /// Running sum of squared phase errors.
+    sum_phase: f64,
+    /// Most recent signed phase error.
+    last_phase: f64,
+    /// Running maximum ULP error.
+    max_ulps: f64,
+    /// Running sum of ULP errors.
+    sum_ulps: f64,
+    /// Running sums for the least-squares fit of each channel to the reference.
+    fit: Fit,
+}
+
+impl DriftMeter {
+    /// Create a new `DriftMeter` for an oscillator with the given initial phase and
+    /// phase step (in radians.)
+    ///
+    /// These are accepted in double-double precision so that, for example, a step of
+    /// 2πf/f<sub>s</sub> can be given exactly enough to serve as ground truth over very
+    /// long runs.
+    pub fn new<P, S>(phase: P, step: S) -> Self
+        where P: Into<DoubleDouble>, S: Into<DoubleDouble>
+    {
+        DriftMeter {
+            reference: IQOsc::new(phase.into(), step.into()),
+            samples: 0,
+            max_amp: 0.0,
+            sum_amp: 0.0,
+            max_phase: 0.0,
+            sum_phase: 0.0,
+            last_phase: 0.0,
+            max_ulps: 0.0,
+            sum_ulps: 0.0,
+            fit: Fit::default(),
+        }
+    }
+
+    /// Compare the given (sin, cos) output of the oscillator under test against the
+    /// next reference sample.
+    pub fn feed<T: Float>(&mut self, sample: (T, T)) {
+        let (rsin, rcos) = self.reference.next();
+
+        let sin = to_dd(sample.0);
+        let cos = to_dd(sample.1);
+
+        // Since |z|² - 1 = (|z| - 1)(|z| + 1), and |z| ≈ 1.
+        let norm = sin * sin + cos * cos - DoubleDouble::from(1.0);
+        let amp = (norm.to_f64() / 2.0).abs();
+
+        // Compute the angle of z · conj(r).
+        let cross = (sin * rcos - cos * rsin).to_f64();
+        let dot = (cos * rcos + sin * rsin).to_f64();
+        let phase = cross.atan2(dot);
+
+        let ulps = (ulp_err(sample.0, rsin), ulp_err(sample.1, rcos));
+
+        self.samples += 1;
+        self.max_amp = self.max_amp.max(amp);
+        self.sum_amp += amp * amp;
+        self.max_phase = self.max_phase.max(phase.abs());
+        self.sum_phase += phase * phase;
+        self.last_phase = phase;
+        self.max_ulps = self.max_ulps.max(ulps.0).max(ulps.1);
+        self.sum_ulps += ulps.0 + ulps.1;
+        self.fit.feed(cos.to_f64(), sin.to_f64(), rcos.to_f64(), rsin.to_f64());
+    }
+
+    /// Summarize the statistics accumulated so far.
+    pub fn report(&self) -> DriftReport {
+        let n = self.samples.max(1) as f64;
+
+        // With I = g₁cos(R + φ₁) = g₁cos φ₁ cos R - g₁sin φ₁ sin R and
+        // Q = g₂sin(R + φ₂) = g₂sin φ₂ cos R + g₂cos φ₂ sin R.
+        let (ic, is) = self.fit.solve(self.fit.ic, self.fit.is);
+        let (qc, qs) = self.fit.solve(self.fit.qc, self.fit.qs);
+
+        let phase_i = (-is).atan2(ic);
+        let phase_q = qc.atan2(qs);
+        let gain_i = ic.hypot(is);
+        let gain_q = qc.hypot(qs);
+
+        // Wrap the difference into (-π, π].
+        let skew = phase_q - phase_i;
+
+        DriftReport {
+            samples: self.samples,
+            max_amplitude_err: self.max_amp,
+            rms_amplitude_err: (self.sum_amp / n).sqrt(),
+            max_phase_err: self.max_phase,
+            rms_phase_err: (self.sum_phase / n).sqrt(),
+            final_phase_err: self.last_phase,
+            quadrature_err: skew.sin().atan2(skew.cos()),
+            gain_imbalance: if gain_i == 0.0 { 0.0 } else { gain_q / gain_i },
+            max_ulps: self.max_ulps,
+            mean_ulps: self.sum_ulps / (2.0 * n),
+        }
+    }
+}
+
+/// Sums for fitting a channel x ≈ a cos R + b sin R by least squares.
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Fit {
+    /// Σcos²R.
+    cc: f64,
+    /// Σsin²R.
+    ss: f64,
+    /// Σcos R sin R.
+    cs: f64,
+    /// ΣI cos R.
+    ic: f64,
+    /// ΣI sin R.
+    is: f64,
+    /// ΣQ cos R.
+    qc: f64,
+    /// ΣQ sin R.
+    qs: f64,
+}
+
+impl Fit {
+    /// Add the given I and Q samples and reference cos R and sin R to the sums.
+    fn feed(&mut self, i: f64, q: f64, c: f64, s: f64) {
+        self.cc += c * c;
+        self.ss += s * s;
+        self.cs += c * s;
+        self.ic += i * c;
+        self.is += i * s;
+        self.qc += q * c;
+        self.qs += q * s;
+    }
+
+    /// Solve the normal equations for (a, b) given the channel's correlations Σx cos R
+    /// and Σx sin R.
+    fn solve(&self, xc: f64, xs: f64) -> (f64, f64) {
+        let det = self.cc * self.ss - self.cs * self.cs;
+
+        if det == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        ((xc * self.ss - xs * self.cs) / det, (xs * self.cc - xc * self.cs) / det)
+    }
+}
+
+/// Run the given oscillator for the given number of samples alongside a reference with
+/// the given initial phase and phase step, and report the accumulated error.
+///
+/// Any oscillator can be measured through the `next` closure, which returns (sin,
+/// cos) at each step, for example `|| osc.next()` for an `IQOsc`.
+pub fn measure<T, P, S, F>(phase: P, step: S, samples: u64, mut next: F) -> DriftReport
+    where T: Float, P: Into<DoubleDouble>, S: Into<DoubleDouble>, F: FnMut() -> (T, T)
+{
+    let mut meter = DriftMeter::new(phase, step);
+
+    for _ in 0..samples {
+        meter.feed(next());
+    }
+
+    meter.report()
+}
+
+/// Convert the given value exactly to double-double precision.
+fn to_dd<T: Float>(x: T) -> DoubleDouble {
+    DoubleDouble::from(x.to_f64().unwrap())
+}
+
+/// Compute the error of x in ULPs of the exact value rounded to x's type.
+fn ulp_err<T: Float>(x: T, exact: DoubleDouble) -> f64 {
+    let rounded = T::from(exact.to_f64()).unwrap();
+    let (mantissa, exp, _) = rounded.integer_decode();
+
+    // Zero decodes with an exponent one below the smallest subnormal.
+    let exp = if mantissa == 0 { exp + 1 } else { exp };
+
+    (to_dd(x) - exact).abs().to_f64() / (exp as f64).exp2()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI as PI32;
+    use std::f64::consts::PI as PI64;
+
+    #[test]
+    fn test_exact() {
+        // Samples computed directly with trig functions should be within rounding of
+        // the reference.
+        let mut k = 0.0f64;
+        let r = measure(0.5, 0.1, 10000, || {
+            let (sin, cos) = (0.5 + 0.1 * k).sin_cos();
+            k += 1.0;
+            (sin as f32, cos as f32)
+        });
+
+        assert_eq!(r.samples, 10000);
+        assert!(r.max_amplitude_err < 1.0e-7);
+        assert!(r.max_phase_err < 1.0e-7);
+        assert!(r.quadrature_err.abs() < 1.0e-7);
+        assert!((r.gain_imbalance - 1.0).abs() < 1.0e-7);
+        assert!(r.max_ulps <= 1.0);
+        assert!(r.mean_ulps <= 0.5);
+    }
+
+    #[test]
+    fn test_osc_32() {
+        let mut o = IQOsc::new(0.0, PI32 / 20.0);
+        let r = measure(0.0, DoubleDouble::PI / DoubleDouble::from(20.0), 40 * 1024,
+                        || o.next());
+
+        assert!(r.max_amplitude_err < 1.0e-3);
+        assert!(r.max_phase_err < 1.0e-3);
+        assert!(r.rms_phase_err <= r.max_phase_err);
+        assert!(r.rms_amplitude_err <= r.max_amplitude_err);
+        assert!(r.quadrature_err.abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_osc_64() {
+        let mut o = IQOsc::new(0.0, PI64 / 20.0);
+        let r = measure(0.0, DoubleDouble::PI / DoubleDouble::from(20.0), 40 * 8192,
+                        || o.next());
+
+        assert!(r.max_amplitude_err < 1.0e-12);
+        assert!(r.max_phase_err < 1.0e-12);
+        assert!(r.max_amplitude_err > 0.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        extern crate bincode;
+
+        // Checkpoint a measurement halfway and resume it.
+        let mut o = IQOsc::new(0.0, PI64 / 20.0);
+        let mut meter = DriftMeter::new(0.0, PI64 / 20.0);
+
+        for _ in 0..5000 {
+            meter.feed(o.next());
+        }
+
+        let mut restored: DriftMeter =
+            bincode::deserialize(&bincode::serialize(&meter).unwrap()).unwrap();
+
+        for _ in 0..5000 {
+            let s = o.next();
+            meter.feed(s);
+            restored.feed(s);
+        }
+
+        assert_eq!(meter.report(), restored.report());
+    }
+
+    #[test]
+    fn test_skew() {
+        // Delay the Q channel by 0.01 rad and scale it by 1.1.
+        let mut k = 0.0f64;
+        let r = measure(0.0, PI64 / 8.0, 8000, || {
+            let phase = PI64 / 8.0 * k;
+            k += 1.0;
+            (1.1 * (phase - 0.01).sin(), phase.cos())
+        });
+
+        assert!((r.quadrature_err - -0.01).abs() < 1.0e-9);
+        assert!((r.gain_imbalance - 1.1).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_skew_wrap() {
+        // Against a reference offset by π, the channel phases straddle ±π.
+        let mut k = 0.0f64;
+        let r = measure(PI64, PI64 / 8.0, 8000, || {
+            let phase = PI64 / 8.0 * k;
+            k += 1.0;
+            ((phase - 0.01).sin(), phase.cos())
+        });
+
+        assert!((r.quadrature_err - -0.01).abs() < 1.0e-9);
+    }
+}
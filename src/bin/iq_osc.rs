@@ -0,0 +1,548 @@
+//! Generate and frequency-shift test signals from the command line.
+//!
+//! Run `iq_osc help` for usage.
+
+extern crate iq_osc;
+
+use std::env;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::process;
+
+use iq_osc::{IQOsc, OscFloat};
+use iq_osc::chirp::Chirp;
+use iq_osc::dd::DoubleDouble;
+use iq_osc::iqfile::{Format, IQReader, IQWriter};
+use iq_osc::multitone::{MultiTone, Phases};
+use iq_osc::wav::{SampleFormat, WavSpec, WavWriter};
+
+const USAGE: &str = "\
+usage: iq_osc COMMAND [OPTIONS]
+
+commands:
+  tone        generate a single tone
+  chirp       generate a linear chirp
+  multitone   generate a sum of tones
+  xlate       shift the frequency of a raw I/Q stream by --freq
+  help        print this message
+
+options:
+  -f, --freq HZ              tone frequency or xlate offset [default: 1000]
+  --start HZ                 chirp start frequency [default: 0]
+  --stop HZ                  chirp stop frequency [default: rate / 4]
+  -t, --tone HZ[:AMP[:DEG]]  add a tone to a multitone signal (repeatable)
+  --phases NAME              multitone phase preset added to each tone's phase:
+                             zero, schroeder, or newman [default: zero]
+  -r, --rate HZ              sample rate [default: 48000]
+  -d, --duration SECS        signal duration [default: 1]
+  -a, --amplitude AMP        peak amplitude, with full scale at 1.0, or xlate gain
+                             [default: 1]
+  -p, --phase DEG            initial oscillator phase [default: 0]
+  -b, --backend NAME         f32, f64, dd, or trig [default: f64]
+  -F, --format NAME          cf32, cf64, cs16, cs8, cu8, wav16, wav24, wavf32, or csv
+                             [default: cf32, or the input format for xlate]
+  -i, --input PATH           xlate input file [default: stdin]
+  -I, --input-format NAME    xlate input format: cf32, cf64, cs16, cs8, or cu8
+                             [default: cf32]
+  --mono                     write only I to WAV outputs
+  -o, --output PATH          output file [default: stdout]
+";
+
+/// Number of samples generated or processed at a time.
+const BLOCK: usize = 4096;
+
+/// Oscillator implementation used to generate samples.
+#[derive(Copy, Clone)]
+enum Backend {
+    /// `IQOsc<f32>`.
+    F32,
+    /// `IQOsc<f64>`.
+    F64,
+    /// `IQOsc<DoubleDouble>`.
+    DD,
+    /// Direct `sin_cos()` calls on the f64 phase at each sample, for reference.
+    Trig,
+}
+
+/// Output encoding.
+#[derive(Copy, Clone)]
+enum Output {
+    /// Raw interleaved I/Q.
+    Raw(Format),
+    /// WAV audio.
+    Wav(SampleFormat),
+    /// Comma-separated I and Q lines.
+    Csv,
+}
+
+/// Tone in a multitone signal.
+struct Tone {
+    /// Frequency in Hz.
+    freq: f64,
+    /// Relative amplitude.
+    amp: f64,
+    /// Initial phase in radians.
+    phase: f64,
+}
+
+/// Parsed command-line options.
+struct Opts {
+    cmd: String,
+    freq: f64,
+    start: f64,
+    stop: Option<f64>,
+    tones: Vec<Tone>,
+    phases: Phases,
+    rate: f64,
+    duration: f64,
+    amplitude: f64,
+    phase: f64,
+    backend: Backend,
+    output: Option<Output>,
+    mono: bool,
+    path: Option<String>,
+    input: Option<String>,
+    input_format: Format,
+}
+
+impl Opts {
+    /// Retrieve the output encoding, defaulting to raw I/Q in the input format.
+    fn output(&self) -> Output {
+        self.output.unwrap_or(Output::Raw(self.input_format))
+    }
+}
+
+/// Generates (sin, cos) samples.
+type Source = Box<dyn FnMut() -> (f64, f64)>;
+
+fn main() {
+    let opts = match parse(env::args().skip(1).collect()) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("iq_osc: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        },
+    };
+
+    if let Err(e) = run(&opts) {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("iq_osc: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Parse the given arguments.
+fn parse(args: Vec<String>) -> Result<Opts, String> {
+    let mut args = args.into_iter();
+
+    let cmd = match args.next() {
+        Some(c) => c,
+        None => return Err("missing command".to_string()),
+    };
+
+    match &cmd[..] {
+        "tone" | "chirp" | "multitone" | "xlate" => {},
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            process::exit(0);
+        },
+        _ => return Err(format!("unknown command '{}'", cmd)),
+    }
+
+    let mut opts = Opts {
+        cmd,
+        freq: 1000.0,
+        start: 0.0,
+        stop: None,
+        tones: vec![],
+        phases: Phases::Zero,
+        rate: 48000.0,
+        duration: 1.0,
+        amplitude: 1.0,
+        phase: 0.0,
+        backend: Backend::F64,
+        output: None,
+        mono: false,
+        path: None,
+        input: None,
+        input_format: Format::Cf32,
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "--mono" {
+            opts.mono = true;
+            continue;
+        }
+
+        let val = match args.next() {
+            Some(v) => v,
+            None => return Err(format!("missing value for '{}'", arg)),
+        };
+
+        match &arg[..] {
+            "-f" | "--freq" => opts.freq = num(&arg, &val)?,
+            "--start" => opts.start = num(&arg, &val)?,
+            "--stop" => opts.stop = Some(num(&arg, &val)?),
+            "-t" | "--tone" => opts.tones.push(tone(&val)?),
+            "--phases" => opts.phases = match &val[..] {
+                "zero" => Phases::Zero,
+                "schroeder" => Phases::Schroeder,
+                "newman" => Phases::Newman,
+                _ => return Err(format!("unknown phase preset '{}'", val)),
+            },
+            "-r" | "--rate" => opts.rate = num(&arg, &val)?,
+            "-d" | "--duration" => opts.duration = num(&arg, &val)?,
+            "-a" | "--amplitude" => opts.amplitude = num(&arg, &val)?,
+            "-p" | "--phase" => opts.phase = num::<f64>(&arg, &val)?.to_radians(),
+            "-b" | "--backend" => opts.backend = match &val[..] {
+                "f32" => Backend::F32,
+                "f64" => Backend::F64,
+                "dd" => Backend::DD,
+                "trig" => Backend::Trig,
+                _ => return Err(format!("unknown backend '{}'", val)),
+            },
+            "-F" | "--format" => opts.output = Some(match &val[..] {
+                "wav16" => Output::Wav(SampleFormat::Pcm16),
+                "wav24" => Output::Wav(SampleFormat::Pcm24),
+                "wavf32" => Output::Wav(SampleFormat::Float32),
+                "csv" => Output::Csv,
+                _ => match val.parse() {
+                    Ok(f) => Output::Raw(f),
+                    Err(_) => return Err(format!("unknown format '{}'", val)),
+                },
+            }),
+            "-I" | "--input-format" => opts.input_format = match val.parse() {
+                Ok(f) => f,
+                Err(e) => return Err(e.to_string()),
+            },
+            "-i" | "--input" => opts.input = Some(val),
+            "-o" | "--output" => opts.path = Some(val),
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    if !opts.rate.is_finite() || opts.rate <= 0.0 {
+        return Err("sample rate must be finite and positive".to_string());
+    }
+
+    if !opts.duration.is_finite() || opts.duration < 0.0 {
+        return Err("duration must be finite and non-negative".to_string());
+    }
+
+    if opts.cmd == "multitone" && opts.tones.is_empty() {
+        return Err("multitone requires at least one --tone".to_string());
+    }
+
+    Ok(opts)
+}
+
+/// Parse the given option value as a number.
+fn num<T: std::str::FromStr>(arg: &str, val: &str) -> Result<T, String> {
+    val.parse().map_err(|_| format!("invalid value '{}' for '{}'", val, arg))
+}
+
+/// Parse a tone of the form `HZ[:AMP[:DEG]]`.
+fn tone(val: &str) -> Result<Tone, String> {
+    let mut parts = val.split(':');
+
+    let freq = num("--tone", parts.next().unwrap())?;
+    let amp = parts.next().map_or(Ok(1.0), |a| num("--tone", a))?;
+    let phase = parts.next().map_or(Ok(0.0), |p| num::<f64>("--tone", p))?;
+
+    if parts.next().is_some() {
+        return Err(format!("invalid tone '{}'", val));
+    }
+
+    Ok(Tone { freq, amp, phase: phase.to_radians() })
+}
+
+/// Create a source for a tone at the given initial phase and phase step (in radians.)
+fn tone_source(backend: Backend, phase: f64, step: f64) -> Source {
+    match backend {
+        Backend::F32 => {
+            let mut osc = IQOsc::new(phase as f32, step as f32);
+            Box::new(move || {
+                let (sin, cos) = osc.next();
+                (sin as f64, cos as f64)
+            })
+        },
+        Backend::F64 => {
+            let mut osc = IQOsc::new(phase, step);
+            Box::new(move || osc.next())
+        },
+        Backend::DD => {
+            let mut osc = IQOsc::new(DoubleDouble::from(phase), DoubleDouble::from(step));
+            Box::new(move || {
+                let (sin, cos) = osc.next();
+                (sin.to_f64(), cos.to_f64())
+            })
+        },
+        Backend::Trig => {
+            let mut t = 0u64;
+            Box::new(move || {
+                let x = phase + step * t as f64;
+                t += 1;
+                x.sin_cos()
+            })
+        },
+    }
+}
+
+/// Create a source for a chirp at the given initial phase, initial phase step, and
+/// sweep rate (in radians.)
+fn chirp_source(backend: Backend, phase: f64, step: f64, rate: f64) -> Source {
+    match backend {
+        Backend::F32 => {
+            let mut c = Chirp::new(phase as f32, step as f32, rate as f32);
+            Box::new(move || {
+                let (sin, cos) = c.next();
+                (sin as f64, cos as f64)
+            })
+        },
+        Backend::F64 => {
+            let mut c = Chirp::new(phase, step, rate);
+            Box::new(move || c.next())
+        },
+        Backend::DD => {
+            let mut c = Chirp::new(DoubleDouble::from(phase), DoubleDouble::from(step),
+                                   DoubleDouble::from(rate));
+            Box::new(move || {
+                let (sin, cos) = c.next();
+                (sin.to_f64(), cos.to_f64())
+            })
+        },
+        Backend::Trig => {
+            let mut t = 0u64;
+            Box::new(move || {
+                let n = t as f64;
+                t += 1;
+                (phase + step * n + rate * n * (n - 1.0) / 2.0).sin_cos()
+            })
+        },
+    }
+}
+
+/// Create a source for the sum of the given (step, amplitude, phase) tones, converting
+/// each output component with the given function.
+fn multitone_source<T>(tones: &[(f64, f64, f64)], conv: fn(T) -> f64) -> Source
+    where T: OscFloat + 'static
+{
+    let mut m = MultiTone::new();
+
+    for &(step, amp, phase) in tones {
+        m.add_tone(T::from_f64(step), T::from_f64(amp), T::from_f64(phase));
+    }
+
+    Box::new(move || {
+        let (sin, cos) = m.next();
+        (conv(sin), conv(cos))
+    })
+}
+
+/// Create the source of the requested signal, scaled by the requested amplitude.
+fn source(opts: &Opts, samples: u64) -> Source {
+    let step = |f: f64| 2.0 * PI * f / opts.rate;
+    let amp = opts.amplitude;
+
+    let mut src = match &opts.cmd[..] {
+        "tone" => tone_source(opts.backend, opts.phase, step(opts.freq)),
+        "chirp" => {
+            let start = step(opts.start);
+            let stop = step(opts.stop.unwrap_or(opts.rate / 4.0));
+            let rate = (stop - start) / samples.max(1) as f64;
+
+            chirp_source(opts.backend, opts.phase, start, rate)
+        },
+        _ => {
+            let amps: Vec<f64> = opts.tones.iter().map(|t| t.amp).collect();
+            let tones: Vec<(f64, f64, f64)> = opts.tones.iter()
+                .zip(opts.phases.compute(&amps))
+                .map(|(t, p)| (step(t.freq), t.amp, opts.phase + t.phase + p))
+                .collect();
+
+            match opts.backend {
+                Backend::F32 => multitone_source(&tones, |x: f32| x as f64),
+                Backend::F64 => multitone_source(&tones, |x: f64| x),
+                Backend::DD => multitone_source(&tones, |x: DoubleDouble| x.to_f64()),
+                Backend::Trig => {
+                    let mut srcs: Vec<(f64, Source)> = tones.iter()
+                        .map(|&(step, amp, phase)| {
+                            (amp, tone_source(Backend::Trig, phase, step))
+                        })
+                        .collect();
+
+                    Box::new(move || srcs.iter_mut().fold((0.0, 0.0), |(s, c), t| {
+                        let (sin, cos) = (t.1)();
+                        (s + t.0 * sin, c + t.0 * cos)
+                    }))
+                },
+            }
+        },
+    };
+
+    Box::new(move || {
+        let (sin, cos) = src();
+        (amp * sin, amp * cos)
+    })
+}
+
+/// Encodes samples to an output stream.
+enum Sink<W: Write> {
+    Raw(IQWriter<W>),
+    Wav(WavWriter<W>),
+    Csv(W),
+}
+
+impl<W: Write> Sink<W> {
+    /// Create a new `Sink` over the given writer in the requested output format.
+    fn new(out: W, opts: &Opts) -> io::Result<Self> {
+        Ok(match opts.output() {
+            Output::Raw(format) => {
+                let mut w = IQWriter::new(out, format);
+                w.set_clip(true);
+                Sink::Raw(w)
+            },
+            Output::Wav(format) =>
+                Sink::Wav(WavWriter::new(out, wav_spec(opts, format)?)?),
+            Output::Csv => Sink::Csv(out),
+        })
+    }
+
+    /// Write the given (sin, cos) samples.
+    fn write(&mut self, samples: &[(f64, f64)]) -> io::Result<()> {
+        match *self {
+            Sink::Raw(ref mut w) => w.write_samples(samples),
+            Sink::Wav(ref mut w) => w.write_samples(samples),
+            Sink::Csv(ref mut w) => {
+                for &(sin, cos) in samples {
+                    writeln!(w, "{},{}", cos, sin)?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    /// Flush the output, leaving any WAV header with unknown sizes.
+    fn close(self) -> io::Result<()> {
+        match self {
+            Sink::Raw(w) => w.into_inner().flush(),
+            Sink::Wav(w) => w.into_inner().flush(),
+            Sink::Csv(mut w) => w.flush(),
+        }
+    }
+}
+
+impl<W: Write + Seek> Sink<W> {
+    /// Flush the output, filling in the sizes of any WAV header.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Wav(w) => w.finish()?.flush(),
+            s => s.close(),
+        }
+    }
+}
+
+/// Run the requested command.
+fn run(opts: &Opts) -> io::Result<()> {
+    match opts.path {
+        Some(ref path) => {
+            // Reject a bad WAV layout before truncating the output file.
+            if let Output::Wav(format) = opts.output() {
+                wav_spec(opts, format)?;
+            }
+
+            let mut sink = Sink::new(BufWriter::new(File::create(path)?), opts)?;
+            produce(opts, &mut sink)?;
+            sink.finish()
+        },
+        None => {
+            let stdout = io::stdout();
+            let mut sink = Sink::new(BufWriter::new(stdout.lock()), opts)?;
+            produce(opts, &mut sink)?;
+            sink.close()
+        },
+    }
+}
+
+/// Write the output of the requested command to the given sink, one block at a time.
+fn produce<W: Write>(opts: &Opts, sink: &mut Sink<W>) -> io::Result<()> {
+    let mut block = Vec::with_capacity(BLOCK);
+
+    if opts.cmd == "xlate" {
+        return xlate(opts, sink, &mut block);
+    }
+
+    let samples = (opts.duration * opts.rate).round() as u64;
+    let mut next = source(opts, samples);
+    let mut left = samples;
+
+    while left > 0 {
+        let n = left.min(BLOCK as u64);
+
+        block.clear();
+        block.extend((0..n).map(|_| next()));
+        sink.write(&block)?;
+
+        left -= n;
+    }
+
+    Ok(())
+}
+
+/// Shift the frequency of the input stream by mixing it with an oscillator.
+///
+/// The oscillator runs continuously from block to block, so the shifted stream has no
+/// phase discontinuities at block boundaries.
+fn xlate<W: Write>(opts: &Opts, sink: &mut Sink<W>, block: &mut Vec<(f64, f64)>)
+    -> io::Result<()>
+{
+    let input: Box<dyn Read> = match opts.input {
+        Some(ref path) if path != "-" => Box::new(File::open(path)?),
+        _ => Box::new(io::stdin()),
+    };
+
+    let mut r = IQReader::new(BufReader::new(input), opts.input_format);
+    let mut osc = tone_source(opts.backend, opts.phase, 2.0 * PI * opts.freq / opts.rate);
+    let amp = opts.amplitude;
+
+    block.resize(BLOCK, (0.0, 0.0));
+
+    loop {
+        let n = r.read_samples(&mut block[..])?;
+
+        if n == 0 {
+            return Ok(());
+        }
+
+        // Multiply each input sample i + jq by the oscillator phasor cos + j sin.
+        for s in &mut block[..n] {
+            let (q, i) = *s;
+            let (sin, cos) = osc();
+
+            *s = (amp * (i * sin + q * cos), amp * (i * cos - q * sin));
+        }
+
+        sink.write(&block[..n])?;
+    }
+}
+
+/// Create the WAV layout for the given options.
+fn wav_spec(opts: &Opts, format: SampleFormat) -> io::Result<WavSpec> {
+    if opts.rate.fract() != 0.0 || opts.rate > u32::MAX as f64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "WAV sample rate must be a 32-bit integer"));
+    }
+
+    let spec = WavSpec {
+        stereo: !opts.mono,
+        sample_rate: opts.rate as u32,
+        format,
+    };
+
+    spec.validate()?;
+
+    Ok(spec)
+}
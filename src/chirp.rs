@@ -0,0 +1,117 @@
+//! Linear chirp oscillator without trig function calls.
+//!
+//! A linear chirp has phase
+//!
+//! > Φ(t) = θ<sub>0</sub> + ω<sub>0</sub>t + αt(t - 1)/2,
+//!
+//! so its phase step ω(t) = Φ(t + 1) - Φ(t) = ω<sub>0</sub> + αt itself advances by a
+//! constant α at each step. The step phasor (sin ω(t), cos ω(t)) can then be generated
+//! by an `IQOsc`, and each sample of the chirp is found by rotating the previous sample
+//! by the current step phasor, for a total of 2 complex multiplies per sample.
+//!
+//! Since any error in the step phasor is accumulated into the phase at every following
+//! step, roundoff error grows faster than for an `IQOsc` with a fixed step.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use {IQOsc, OscFloat};
+
+/// Quadrature linear chirp with current phase Φ(t), phase step ω(t), and sweep rate α.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Chirp<T: OscFloat> {
+    /// Generates (sin ω(t), cos ω(t)) for the current phase step ω(t).
+    step: IQOsc<T>,
+    /// Holds (sin Φ(t), cos Φ(t)) for the current phase Φ(t).
+    phase: (T, T),
+}
+
+impl<T: OscFloat> Chirp<T> {
+    /// Create a new `Chirp` starting at the given initial phase θ<sub>0</sub> and phase
+    /// step ω<sub>0</sub>, with the phase step increasing by the given rate α at each
+    /// step (all in radians.)
+    pub fn new(phase: T, step: T, rate: T) -> Self {
+        Chirp {
+            step: IQOsc::new(step, rate),
+            phase: phase.sin_cos(),
+        }
+    }
+
+    /// Create a new `Chirp` that sweeps linearly from the given initial phase step to
+    /// the given final phase step (in radians) over the given number of samples.
+    pub fn sweep(phase: T, start: T, stop: T, samples: T) -> Self {
+        Chirp::new(phase, start, (stop - start) / samples)
+    }
+
+    /// Step the phase to Φ(t+1) and return (sin Φ(t), cos Φ(t)).
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> (T, T) {
+        let cur = self.phase;
+        let (ssin, scos) = self.step.next();
+
+        self.phase = (
+            self.phase.0 * scos + self.phase.1 * ssin,
+            self.phase.1 * scos - self.phase.0 * ssin,
+        );
+
+        cur
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use dd::DoubleDouble;
+
+    #[test]
+    fn test_chirp() {
+        let mut c = Chirp::new(0.25, 0.1, 0.001);
+
+        for t in 0..2000 {
+            let t = t as f64;
+            let (sin, cos) = (0.25 + 0.1 * t + 0.001 * t * (t - 1.0) / 2.0).sin_cos();
+            let (csin, ccos) = c.next();
+
+            assert!((sin - csin).abs() < 1.0e-10);
+            assert!((cos - ccos).abs() < 1.0e-10);
+        }
+    }
+
+    #[test]
+    fn test_sweep() {
+        // Sweep from -π/2 to π/2 over 1000 samples.
+        let mut c = Chirp::sweep(0.0f32, -PI as f32 / 2.0, PI as f32 / 2.0, 1000.0);
+        let mut prev = c.next();
+
+        for t in 1..1000 {
+            let cur = c.next();
+
+            // Compute the angle between successive samples.
+            let step = (cur.0 * prev.1 - cur.1 * prev.0)
+                .atan2(cur.1 * prev.1 + cur.0 * prev.0);
+            let expected = -PI / 2.0 + PI * (t - 1) as f64 / 1000.0;
+            assert!((step as f64 - expected).abs() < 1.0e-4);
+
+            prev = cur;
+        }
+    }
+
+    #[test]
+    fn test_dd() {
+        // Compare to the quadratic phase of the chirp exp(-jπt²/N).
+        let n = DoubleDouble::from(1000.0);
+        let mut c = Chirp::new(DoubleDouble::from(0.0), -DoubleDouble::PI / n,
+                               -(DoubleDouble::PI + DoubleDouble::PI) / n);
+
+        for t in 0..5000 {
+            let t = DoubleDouble::from(t as f64);
+            let (sin, cos) = (-DoubleDouble::PI * t * t / n).sin_cos();
+            let (csin, ccos) = c.next();
+
+            assert!((sin - csin).abs().to_f64() < 1.0e-25);
+            assert!((cos - ccos).abs().to_f64() < 1.0e-25);
+        }
+    }
+}
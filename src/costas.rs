@@ -0,0 +1,278 @@
+//! Costas loops for PSK carrier recovery.
+//!
+//! A Costas loop is a `pll::Pll` with a phase detector that ignores the data
+//! modulation. For M-ary PSK, the symbols lie at multiples of 2π/M, so raising the
+//! normalized derotated symbol u to the M-th power removes the modulation, and
+//!
+//! > e = arg(u<sup>M</sup>)/M
+//!
+//! gives the phase error in the range (-π/M, π/M]. Since the detector can't tell the
+//! symbols apart, the loop locks with an ambiguity of a multiple of 2π/M, which has to
+//! be resolved by the framing or differential coding of the data.
+//!
+//! The loop runs at one sample per symbol, so its input should already be matched
+//! filtered and sampled at the symbol times. The loop bandwidth is then in radians per
+//! symbol.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use pll::Pll;
+
+/// PSK modulation tracked by a Costas loop.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Modulation {
+    /// Binary PSK with symbols ±1.
+    Bpsk,
+    /// Quadrature PSK with symbols (±1 ± j)/√2.
+    Qpsk,
+    /// 8-ary PSK with symbols e<sup>jπk/4</sup>.
+    Psk8,
+}
+
+impl Modulation {
+    /// Retrieve the number of symbols M.
+    pub fn order(&self) -> usize {
+        match *self {
+            Modulation::Bpsk => 2,
+            Modulation::Qpsk => 4,
+            Modulation::Psk8 => 8,
+        }
+    }
+}
+
+/// Costas loop for BPSK, QPSK, or 8PSK.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Costas<T: Float> {
+    /// Tracks the carrier.
+    pll: Pll<T>,
+    /// Modulation of the symbols.
+    modulation: Modulation,
+}
+
+impl<T: Float> Costas<T> {
+    /// Create a new `Costas` loop for the given modulation, starting at the given phase
+    /// step ω (in radians per symbol) with the given loop bandwidth B<sub>n</sub> (in
+    /// radians per symbol) and damping factor ζ.
+    ///
+    /// The loop can only pull in frequency offsets that are well within the loop
+    /// bandwidth and π/M radians per symbol.
+    pub fn new(modulation: Modulation, step: T, bandwidth: T, damping: T) -> Self {
+        Costas {
+            pll: Pll::new(step, bandwidth, damping),
+            modulation,
+        }
+    }
+
+    /// Retrieve the modulation.
+    pub fn modulation(&self) -> Modulation {
+        self.modulation
+    }
+
+    /// Change the loop bandwidth B<sub>n</sub> (in radians per symbol) and damping
+    /// factor ζ, keeping the current phase and frequency.
+    pub fn set_bandwidth(&mut self, bandwidth: T, damping: T) {
+        self.pll.set_bandwidth(bandwidth, damping);
+    }
+
+    /// Change the lock detector to smooth cos Me with the given factor and report lock
+    /// above the given threshold, as with `Pll::set_lock_detector()`.
+    pub fn set_lock_detector(&mut self, smoothing: T, threshold: T) {
+        self.pll.set_lock_detector(smoothing, threshold);
+    }
+
+    /// Retrieve the current frequency estimate, the phase step ω of the NCO (in radians
+    /// per symbol.)
+    pub fn freq(&self) -> T {
+        self.pll.freq()
+    }
+
+    /// Retrieve the phase error e of the last symbol (in radians.)
+    pub fn phase_error(&self) -> T {
+        self.pll.phase_error()
+    }
+
+    /// Retrieve the smoothed cos Me of the lock detector, in the range [-1, 1].
+    pub fn lock_metric(&self) -> T {
+        self.pll.lock_metric()
+    }
+
+    /// Check if the loop is locked.
+    pub fn is_locked(&self) -> bool {
+        self.pll.is_locked()
+    }
+
+    /// Feed the given (sin, cos) symbol and update the loop, returning the derotated
+    /// symbol as (sin, cos).
+    pub fn feed(&mut self, sample: (T, T)) -> (T, T) {
+        let (re, im) = self.pll.derotate(sample);
+        let mag = re.hypot(im);
+
+        if mag > T::zero() {
+            let (er, ei) = power((re / mag, im / mag), self.modulation);
+            let order = T::from(self.modulation.order()).unwrap();
+
+            self.pll.correct(ei.atan2(er) / order, er);
+        } else {
+            self.pll.correct(T::zero(), T::zero());
+        }
+
+        (im, re)
+    }
+}
+
+/// Compute the given (re, im) symbol to the M-th power of the given modulation,
+/// rotated so the ideal symbols map to 1.
+fn power<T: Float>(u: (T, T), modulation: Modulation) -> (T, T) {
+    let square = |(re, im): (T, T)| (re * re - im * im, (re + re) * im);
+
+    match modulation {
+        Modulation::Bpsk => square(u),
+        // The symbols at π/4 + kπ/2 map to e^(jπ) = -1.
+        Modulation::Qpsk => {
+            let (re, im) = square(square(u));
+            (-re, -im)
+        },
+        Modulation::Psk8 => square(square(square(u))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use IQOsc;
+    use testutil::Rng;
+
+    /// Compute the phase of the given symbol index of the given modulation.
+    fn symbol_phase(modulation: Modulation, k: usize) -> f64 {
+        match modulation {
+            Modulation::Bpsk => PI * k as f64,
+            Modulation::Qpsk => PI / 4.0 + PI / 2.0 * k as f64,
+            Modulation::Psk8 => PI / 4.0 * k as f64,
+        }
+    }
+
+    /// Decide the symbol index nearest the given (sin, cos) symbol.
+    fn decide(modulation: Modulation, (sin, cos): (f64, f64)) -> usize {
+        let m = modulation.order();
+        let offset = symbol_phase(modulation, 0);
+        let phase = sin.atan2(cos) - offset;
+
+        ((phase / (2.0 * PI / m as f64)).round() as isize).rem_euclid(m as isize) as usize
+    }
+
+    /// Run the given loop on random symbols with the given carrier step, phase, and
+    /// noise, and return the number of symbol errors after the given number of symbols,
+    /// allowing for the phase ambiguity.
+    fn run(costas: Costas<f64>, step: f64, phase: f64, sigma: f64, settle: usize)
+        -> (Costas<f64>, usize)
+    {
+        let mut costas = costas;
+        let modulation = costas.modulation();
+        let m = modulation.order();
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        let mut carrier = IQOsc::new(phase, step);
+        let mut rotation = None;
+        let mut errors = 0;
+
+        for n in 0..20_000 {
+            let k = (rng.next_u64() >> 32) as usize % m;
+            let (csin, ccos) = carrier.next();
+            let (ssin, scos) = symbol_phase(modulation, k).sin_cos();
+            let (nsin, ncos) = rng.noise(sigma);
+
+            // Rotate the symbol by the carrier and add noise.
+            let sample = (
+                ssin * ccos + scos * csin + nsin,
+                scos * ccos - ssin * csin + ncos,
+            );
+            let out = costas.feed(sample);
+
+            if n < settle {
+                continue;
+            }
+
+            let got = decide(modulation, out);
+            let rot = *rotation.get_or_insert((got + m - k) % m);
+
+            if (k + rot) % m != got {
+                errors += 1;
+            }
+        }
+
+        (costas, errors)
+    }
+
+    #[test]
+    fn test_offset() {
+        for &modulation in &[Modulation::Bpsk, Modulation::Qpsk, Modulation::Psk8] {
+            for &(step, phase) in &[(0.01, 0.3), (-0.02, 2.0), (0.005, -1.0)] {
+                let costas = Costas::new(modulation, 0.0, 0.02, 0.707);
+                let (costas, errors) = run(costas, step, phase, 0.0, 2000);
+
+                assert_eq!(errors, 0, "{:?} {}", modulation, step);
+                assert!((costas.freq() - step).abs() < 1.0e-9, "{:?}", modulation);
+                assert!(costas.phase_error().abs() < 1.0e-9);
+                assert!(costas.is_locked());
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise() {
+        // At 20 dB SNR, even 8PSK symbols come through without errors.
+        for &modulation in &[Modulation::Bpsk, Modulation::Qpsk, Modulation::Psk8] {
+            let costas = Costas::new(modulation, 0.0, 0.02, 0.707);
+            let (costas, errors) = run(costas, 0.015, 1.0, 0.0707, 3000);
+
+            assert_eq!(errors, 0, "{:?}", modulation);
+            assert!((costas.freq() - 0.015).abs() < 2.0e-3, "{:?}", modulation);
+            assert!(costas.is_locked());
+        }
+    }
+
+    #[test]
+    fn test_wide() {
+        // Wide loops take large corrections while acquiring without diverging.
+        for &modulation in &[Modulation::Bpsk, Modulation::Qpsk, Modulation::Psk8] {
+            for &(bandwidth, damping) in &[(0.5, 1.0), (0.8, 0.707)] {
+                let costas = Costas::new(modulation, 0.0, bandwidth, damping);
+                let (costas, errors) = run(costas, 0.05, 2.5, 0.0, 500);
+
+                assert_eq!(errors, 0, "{:?} {}", modulation, bandwidth);
+                assert!((costas.freq() - 0.05).abs() < 1.0e-9, "{:?}", modulation);
+                assert!(costas.is_locked());
+            }
+        }
+    }
+
+    #[test]
+    fn test_symbols() {
+        // Derotated symbols land on the constellation, and a plain PLL doesn't lock.
+        let mut carrier = IQOsc::new(0.5f32, 0.01);
+        let mut costas = Costas::new(Modulation::Qpsk, 0.0, 0.05, 1.0);
+        let mut pll = Pll::new(0.0, 0.05, 1.0);
+        let mut last = (0.0, 0.0);
+
+        for n in 0..4000 {
+            let (ssin, scos) = (PI / 4.0 + PI / 2.0 * (n * 7 % 4) as f64).sin_cos();
+            let (ssin, scos) = (ssin as f32, scos as f32);
+            let (csin, ccos) = carrier.next();
+            let sample = (ssin * ccos + scos * csin, scos * ccos - ssin * csin);
+
+            last = costas.feed(sample);
+            pll.feed(sample);
+        }
+
+        assert!((last.0.abs() - 0.5f32.sqrt()).abs() < 1.0e-4);
+        assert!((last.1.abs() - 0.5f32.sqrt()).abs() < 1.0e-4);
+        assert!(costas.lock_metric() > 0.99);
+        assert!(!pll.is_locked());
+        assert_eq!(costas.modulation().order(), 4);
+    }
+}
@@ -0,0 +1,289 @@
+//! Chirp Z-transform and zoom spectrum.
+//!
+//! The chirp Z-transform (CZT) evaluates the Z-transform of N samples x(n),
+//!
+//! > X(z) = Σ x(n)z<sup>-n</sup>, n = 0, ..., N - 1,
+//!
+//! at M points z<sub>k</sub> = AW<sup>-k</sup>, k = 0, ..., M - 1, along a spiral arc
+//! of the complex plane. On the unit circle, this gives M DFT bins at any spacing
+//! around any frequency, so a narrow band around a known carrier can be examined at
+//! a much finer resolution than an N-point DFT.
+//!
+//! Using nk = (n² + k² - (k - n)²)/2, the transform becomes a convolution with the
+//! chirp W<sup>-m²/2</sup> (Bluestein's algorithm), which is computed with FFTs. The
+//! chirp phases are generated by a `chirp::Chirp` in double-double precision rather
+//! than by evaluating the quadratic phase, which loses precision as m² grows, at each
+//! sample. Small transforms are instead evaluated directly, with an `IQOsc` for each
+//! point.
+//!
+//! This module requires the `std` feature.
+
+use num_traits::Float;
+
+use chirp::Chirp;
+use dd::DoubleDouble;
+use goertzel::Bin;
+use spectrum::fft;
+use IQOsc;
+
+/// Largest product N·M evaluated directly rather than with FFTs.
+const DIRECT_MAX: usize = 1 << 14;
+
+/// Spiral contour of points
+/// z<sub>k</sub> = r<sub>0</sub>ρ<sup>k</sup>e<sup>j(θ<sub>0</sub> + φk)</sup>.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Contour {
+    /// Radius r<sub>0</sub> of the first point.
+    pub radius: f64,
+    /// Angle θ<sub>0</sub> of the first point (in radians).
+    pub angle: f64,
+    /// Ratio ρ of the radii of successive points.
+    pub radius_step: f64,
+    /// Angle φ between successive points (in radians).
+    pub angle_step: f64,
+}
+
+impl Contour {
+    /// Create a new `Contour` along the unit circle, starting at the given angle and
+    /// advancing by the given angle (in radians.)
+    pub fn arc(angle: f64, angle_step: f64) -> Self {
+        Contour {
+            radius: 1.0,
+            angle,
+            radius_step: 1.0,
+            angle_step,
+        }
+    }
+}
+
+/// Evaluate the Z-transform of the given (sin, cos) samples at the given number of
+/// points along the given contour.
+///
+/// Each result holds X(z<sub>k</sub>), with `samples` set to N.
+pub fn czt<T: Float>(samples: &[(T, T)], points: usize, contour: Contour)
+    -> Vec<Bin<f64>>
+{
+    let x: Vec<(f64, f64)> = samples.iter()
+        .map(|&(sin, cos)| (cos.to_f64().unwrap(), sin.to_f64().unwrap()))
+        .collect();
+
+    let out = if x.len() * points <= DIRECT_MAX {
+        direct(&x, points, contour)
+    } else {
+        bluestein(&x, points, contour)
+    };
+
+    out.into_iter().map(|(re, im)| Bin { re, im, samples: x.len() }).collect()
+}
+
+/// Compute a zoomed spectrum of the given (sin, cos) samples with the given number of
+/// bins evenly spaced over the given span (in cycles per sample) around the given
+/// center frequency (in cycles per sample).
+///
+/// Bin k is at frequency center - span/2 + k·span/bins, so the center frequency falls
+/// exactly on bin bins/2 for even bin counts. The samples are used as given, so they
+/// should be windowed first to limit leakage from strong signals outside the span.
+pub fn zoom<T: Float>(samples: &[(T, T)], center: f64, span: f64, bins: usize)
+    -> Vec<Bin<f64>>
+{
+    let tau = 2.0 * core::f64::consts::PI;
+    let start = center - span / 2.0;
+
+    czt(samples, bins, Contour::arc(tau * start, tau * span / bins.max(1) as f64))
+}
+
+/// Evaluate the transform of the given (re, im) samples directly.
+fn direct(x: &[(f64, f64)], points: usize, c: Contour) -> Vec<(f64, f64)> {
+    (0..points).map(|k| {
+        // Step through z_k^(-n) = (r_k e^(jθ_k))^(-n).
+        let angle = c.angle + c.angle_step * k as f64;
+        let ratio = 1.0 / (c.radius * c.radius_step.powi(k as i32));
+
+        let mut osc = IQOsc::new(0.0, -angle);
+        let mut mag = 1.0;
+
+        x.iter().fold((0.0, 0.0), |acc, &(re, im)| {
+            let (sin, cos) = osc.next();
+            let (sin, cos) = (mag * sin, mag * cos);
+            mag *= ratio;
+
+            (acc.0 + re * cos - im * sin, acc.1 + re * sin + im * cos)
+        })
+    }).collect()
+}
+
+/// Evaluate the transform of the given (re, im) samples with Bluestein's algorithm.
+fn bluestein(x: &[(f64, f64)], points: usize, c: Contour) -> Vec<(f64, f64)> {
+    let n = x.len();
+    let len = (n + points - 1).next_power_of_two();
+
+    // With W = ρ^(-1)e^(-jφ), compute W^(m²/2) for m up to the longest sequence.
+    let chirp = chirp(n.max(points), c);
+
+    // Compute y(n) = x(n)A^(-n)W^(n²/2).
+    let mut osc = IQOsc::new(0.0, -c.angle);
+    let mut y = vec![(0.0, 0.0); len];
+
+    for (m, (&(re, im), out)) in x.iter().zip(y.iter_mut()).enumerate() {
+        let (sin, cos) = osc.next();
+        let mag = c.radius.powi(-(m as i32));
+        let w = mul((mag * cos, mag * sin), chirp[m]);
+
+        *out = mul((re, im), w);
+    }
+
+    // Compute the kernel W^(-m²/2) for m = -(N - 1), ..., M - 1, wrapped around.
+    let mut h = vec![(0.0, 0.0); len];
+
+    for m in 0..points {
+        h[m] = inv(chirp[m]);
+    }
+
+    for m in 1..n {
+        h[len - m] = inv(chirp[m]);
+    }
+
+    fft(&mut y);
+    fft(&mut h);
+
+    for (a, &b) in y.iter_mut().zip(&h) {
+        *a = mul(*a, b);
+    }
+
+    ifft(&mut y);
+
+    (0..points).map(|k| mul(y[k], chirp[k])).collect()
+}
+
+/// Compute W<sup>m²/2</sup> for m = 0, ..., count - 1.
+fn chirp(count: usize, c: Contour) -> Vec<(f64, f64)> {
+    // The phase -φm²/2 = -φ(m/2) - φm(m - 1)/2 is a linear chirp.
+    let step = DoubleDouble::from(c.angle_step);
+    let half = step / DoubleDouble::from(-2.0);
+    let mut chirp = Chirp::new(DoubleDouble::from(0.0), half, -step);
+    let log = -c.radius_step.ln() / 2.0;
+
+    (0..count).map(|m| {
+        let (sin, cos) = chirp.next();
+        let mag = (log * (m * m) as f64).exp();
+
+        (mag * cos.to_f64(), mag * sin.to_f64())
+    }).collect()
+}
+
+/// Multiply the given complex numbers.
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+/// Compute the reciprocal of the given complex number.
+fn inv(a: (f64, f64)) -> (f64, f64) {
+    let norm = a.0 * a.0 + a.1 * a.1;
+    (a.0 / norm, -a.1 / norm)
+}
+
+/// Compute the inverse DFT of the given power-of-two length samples in place.
+fn ifft(buf: &mut [(f64, f64)]) {
+    let scale = 1.0 / buf.len() as f64;
+
+    for s in buf.iter_mut() {
+        s.1 = -s.1;
+    }
+
+    fft(buf);
+
+    for s in buf.iter_mut() {
+        *s = (s.0 * scale, -s.1 * scale);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use testutil::{dft, tones};
+
+    /// Evaluate X(z) at the given z = (re, im) by direct summation.
+    fn reference(x: &[(f64, f64)], z: (f64, f64)) -> (f64, f64) {
+        let zinv = inv(z);
+        let mut pow = (1.0, 0.0);
+
+        x.iter().fold((0.0, 0.0), |acc, &(sin, cos)| {
+            let t = mul((cos, sin), pow);
+            pow = mul(pow, zinv);
+            (acc.0 + t.0, acc.1 + t.1)
+        })
+    }
+
+    /// Generate a two-tone test signal.
+    fn signal(len: usize) -> Vec<(f64, f64)> {
+        tones(&[(1.0, 0.3, 0.1234567), (0.5, 1.0, -0.31)], len)
+    }
+
+    fn check(x: &[(f64, f64)], points: usize, c: Contour, tol: f64) {
+        let out = czt(x, points, c);
+        assert_eq!(out.len(), points);
+
+        for (k, bin) in out.iter().enumerate() {
+            let r = c.radius * c.radius_step.powi(k as i32);
+            let (sin, cos) = (c.angle + c.angle_step * k as f64).sin_cos();
+            let (re, im) = reference(x, (r * cos, r * sin));
+
+            assert!((bin.re - re).abs() < tol, "{} {} {}", k, bin.re, re);
+            assert!((bin.im - im).abs() < tol, "{} {} {}", k, bin.im, im);
+            assert_eq!(bin.samples, x.len());
+        }
+    }
+
+    #[test]
+    fn test_dft() {
+        // Points evenly spaced around the unit circle give the DFT.
+        let x = signal(64);
+        let out = czt(&x, 64, Contour::arc(0.0, 2.0 * PI / 64.0));
+
+        for (bin, &(re, im)) in out.iter().zip(&dft(&x)) {
+            assert!((bin.re - re).abs() < 1.0e-9);
+            assert!((bin.im - im).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_direct() {
+        let x = signal(50);
+        check(&x, 40, Contour::arc(0.5, 0.01), 1.0e-9);
+        check(&x, 40, Contour {
+            radius: 1.02,
+            angle: -1.0,
+            radius_step: 0.995,
+            angle_step: 0.05,
+        }, 1.0e-9);
+    }
+
+    #[test]
+    fn test_bluestein() {
+        let x = signal(1000);
+        check(&x, 300, Contour::arc(0.5, 0.001), 1.0e-8);
+        check(&x, 1500, Contour::arc(-3.0, 0.004), 1.0e-8);
+        check(&x[..200], 200, Contour {
+            radius: 1.01,
+            angle: 0.2,
+            radius_step: 0.9999,
+            angle_step: 0.003,
+        }, 1.0e-8);
+    }
+
+    #[test]
+    fn test_zoom() {
+        // Zoom to 1e-6 cycles per sample around the stronger tone.
+        let x = signal(4000);
+        let bins = zoom(&x, 0.1234, 0.0002, 200);
+
+        let peak = (0..bins.len()).fold(0, |max, k| {
+            if bins[k].power() > bins[max].power() { k } else { max }
+        });
+
+        let freq = 0.1234 - 0.0001 + peak as f64 * 0.0002 / 200.0;
+        assert!((freq - 0.1234567).abs() <= 0.5e-6, "{}", freq);
+        assert!((bins[peak].amplitude() - 1.0).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,350 @@
+//! Double-double arithmetic for high-precision reference oscillators.
+//!
+//! A `DoubleDouble` represents a number as the unevaluated sum hi + lo of two `f64`s,
+//! where |lo| ≤ ulp(hi) / 2, giving roughly 106 bits of significand. Only the
+//! operations needed to run the `IQOsc` recurrence and compute its initial phasors are
+//! provided: addition, subtraction, multiplication, division, and `sin_cos`.
+//!
+//! The algorithms follow Hida, Li, and Bailey, "Library for Double-Double and
+//! Quad-Double Arithmetic" (2007), using Dekker's splitting for exact products so no
+//! fused multiply-add is required.
+
+use core::cmp::Ordering;
+use core::f64::consts;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use OscFloat;
+
+/// Largest |x| accepted by `DoubleDouble::sin_cos()`, 2<sup>52</sup>.
+const MAX_SIN_COS_ARG: f64 = 4503599627370496.0;
+
+/// Most terms of each Taylor series summed by `sin_cos_taylor()`.
+const MAX_TAYLOR_TERMS: usize = 40;
+
+/// Number represented as the unevaluated sum of two `f64`s.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DoubleDouble {
+    /// Leading component.
+    hi: f64,
+    /// Trailing component, with |lo| ≤ ulp(hi) / 2.
+    lo: f64,
+}
+
+impl DoubleDouble {
+    /// π to double-double precision.
+    pub const PI: DoubleDouble = DoubleDouble {
+        hi: consts::PI,
+        lo: 1.2246467991473532e-16,
+    };
+
+    /// 2π to double-double precision.
+    pub const TAU: DoubleDouble = DoubleDouble {
+        hi: consts::TAU,
+        lo: 2.4492935982947064e-16,
+    };
+
+    /// π/2 to double-double precision.
+    pub const FRAC_PI_2: DoubleDouble = DoubleDouble {
+        hi: consts::FRAC_PI_2,
+        lo: 6.123233995736766e-17,
+    };
+
+    /// Create a new `DoubleDouble` from the sum of the given components, which are
+    /// renormalized so the result doesn't depend on their relative magnitudes.
+    pub fn new(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+
+    /// Retrieve the leading component.
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    /// Retrieve the trailing component.
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    /// Round to the nearest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.hi + self.lo
+    }
+
+    /// Compute the absolute value.
+    pub fn abs(self) -> Self {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /// Compute (sin x, cos x) for this value x (in radians).
+    ///
+    /// The argument is reduced by the nearest multiple of π/2 and the Taylor series are
+    /// summed to full precision. Accuracy degrades for |x| much larger than
+    /// 2<sup>20</sup> due to the finite precision of the reduction constant, and NaN
+    /// is returned for |x| beyond 2<sup>52</sup>, where the quadrant can't be found.
+    pub fn sin_cos(self) -> (Self, Self) {
+        // The series never converges for NaN.
+        if !Float::is_finite(self.hi) || Float::abs(self.hi) > MAX_SIN_COS_ARG {
+            let nan = DoubleDouble::from(f64::NAN);
+            return (nan, nan);
+        }
+
+        let quad = Float::round((self / Self::FRAC_PI_2).hi);
+        let r = self - Self::FRAC_PI_2 * DoubleDouble::from(quad);
+        let (sin, cos) = sin_cos_taylor(r);
+
+        match quad as i64 & 3 {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        }
+    }
+}
+
+impl OscFloat for DoubleDouble {
+    fn sin_cos(self) -> (Self, Self) {
+        DoubleDouble::sin_cos(self)
+    }
+
+    fn is_finite(self) -> bool {
+        self.hi.is_finite() && self.lo.is_finite()
+    }
+
+    fn abs(self) -> Self {
+        DoubleDouble::abs(self)
+    }
+
+    fn pi() -> Self {
+        DoubleDouble::PI
+    }
+
+    fn from_f64(x: f64) -> Self {
+        DoubleDouble::from(x)
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(x: f64) -> Self {
+        DoubleDouble { hi: x, lo: 0.0 }
+    }
+}
+
+impl From<f32> for DoubleDouble {
+    fn from(x: f32) -> Self {
+        DoubleDouble::from(x as f64)
+    }
+}
+
+impl From<DoubleDouble> for f64 {
+    fn from(x: DoubleDouble) -> Self {
+        x.to_f64()
+    }
+}
+
+impl PartialOrd for DoubleDouble {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ord => ord,
+        }
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (s1, s2) = two_sum(self.hi, rhs.hi);
+        let (t1, t2) = two_sum(self.lo, rhs.lo);
+        let (s1, s2) = quick_two_sum(s1, s2 + t1);
+        let (hi, lo) = quick_two_sum(s1, s2 + t2);
+
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (p1, p2) = two_prod(self.hi, rhs.hi);
+        let (hi, lo) = quick_two_sum(p1, p2 + (self.hi * rhs.lo + self.lo * rhs.hi));
+
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        // Long division with three partial quotients.
+        let q1 = self.hi / rhs.hi;
+        let r = self - rhs * DoubleDouble::from(q1);
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * DoubleDouble::from(q2);
+        let q3 = r.hi / rhs.hi;
+
+        let (hi, lo) = quick_two_sum(q1, q2);
+
+        DoubleDouble { hi, lo } + DoubleDouble::from(q3)
+    }
+}
+
+/// Compute s + e = a + b exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// Compute s + e = a + b exactly, assuming |a| ≥ |b|.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+
+    (s, b - (s - a))
+}
+
+/// Split a into two non-overlapping 26-bit halves.
+fn split(a: f64) -> (f64, f64) {
+    // 2^27 + 1.
+    let t = 134217729.0 * a;
+    let hi = t - (t - a);
+
+    (hi, a - hi)
+}
+
+/// Compute p + e = a × b exactly.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    let (ah, al) = split(a);
+    let (bh, bl) = split(b);
+
+    (p, ((ah * bh - p) + ah * bl + al * bh) + al * bl)
+}
+
+/// Sum the Taylor series of sin x and cos x for |x| ≤ π/4.
+fn sin_cos_taylor(x: DoubleDouble) -> (DoubleDouble, DoubleDouble) {
+    let x2 = x * x;
+
+    let mut sin = x;
+    let mut cos = DoubleDouble::from(1.0);
+
+    // Holds x^(n-1) / (n-1)! for the cosine term and x^n / n! for the sine term.
+    let mut cterm = DoubleDouble::from(1.0);
+    let mut sterm = x;
+    let mut n = 1.0;
+
+    // For the reduced |x| <= π/4, the terms fall below the threshold well before this.
+    for _ in 0..MAX_TAYLOR_TERMS {
+        cterm = -(cterm * x2) / DoubleDouble::from(n * (n + 1.0));
+        sterm = -(sterm * x2) / DoubleDouble::from((n + 1.0) * (n + 2.0));
+        n += 2.0;
+
+        cos = cos + cterm;
+        sin = sin + sterm;
+
+        if Float::abs(cterm.hi) < 1.0e-34 && Float::abs(sterm.hi) < 1.0e-34 {
+            break;
+        }
+    }
+
+    (sin, cos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use IQOsc;
+
+    fn assert_close(a: DoubleDouble, b: DoubleDouble, tol: f64) {
+        assert!((a - b).abs().to_f64() < tol, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_arith() {
+        let third = DoubleDouble::from(1.0) / DoubleDouble::from(3.0);
+        assert_close(third * DoubleDouble::from(3.0), DoubleDouble::from(1.0), 1.0e-31);
+        assert!(third.lo() != 0.0);
+
+        let x = DoubleDouble::new(1.0, 1.0e-20);
+        assert_eq!((x - DoubleDouble::from(1.0)).to_f64(), 1.0e-20);
+        assert!(x > DoubleDouble::from(1.0));
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        let (sin, cos) = DoubleDouble::from(1.0).sin_cos();
+        assert_close(sin, DoubleDouble::new(0.8414709848078965, 1.776845092935536e-18),
+                     1.0e-31);
+        assert_close(cos, DoubleDouble::new(0.5403023058681398, -4.760954612604417e-17),
+                     1.0e-31);
+
+        let (sin, cos) = DoubleDouble::from(100.0).sin_cos();
+        assert_close(sin, DoubleDouble::new(-0.5063656411097588, -3.050947053792115e-18),
+                     1.0e-30);
+        assert_close(cos, DoubleDouble::new(0.8623188722876839, 4.334809858136501e-17),
+                     1.0e-30);
+
+        let (sin, cos) = (DoubleDouble::PI / DoubleDouble::from(6.0)).sin_cos();
+        assert_close(sin, DoubleDouble::from(0.5), 1.0e-31);
+        assert_close(sin * sin + cos * cos, DoubleDouble::from(1.0), 1.0e-31);
+
+        let (sin, cos) = (-DoubleDouble::PI).sin_cos();
+        assert_close(sin, DoubleDouble::from(0.0), 1.0e-31);
+        assert_close(cos, DoubleDouble::from(-1.0), 1.0e-31);
+
+        let (sin, cos) = DoubleDouble::from(f64::INFINITY).sin_cos();
+        assert!(sin.hi().is_nan() && cos.hi().is_nan());
+
+        for &x in &[1.0e20, -1.0e20, 1.0e300] {
+            let (sin, cos) = DoubleDouble::from(x).sin_cos();
+            assert!(sin.hi().is_nan() && cos.hi().is_nan());
+        }
+    }
+
+    #[test]
+    fn test_osc() {
+        let step = DoubleDouble::TAU / DoubleDouble::from(1000.0);
+        let mut o = IQOsc::new(DoubleDouble::from(0.0), step);
+
+        for k in 0..100_000 {
+            let (sin, cos) = o.next();
+            let (esin, ecos) = (step * DoubleDouble::from(k as f64)).sin_cos();
+
+            assert_close(sin, esin, 1.0e-25);
+            assert_close(cos, ecos, 1.0e-25);
+        }
+    }
+}
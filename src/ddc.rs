@@ -0,0 +1,373 @@
+//! Digital down-converter with integrated decimation.
+//!
+//! A `Ddc` shifts a channel at a chosen carrier frequency down to 0 by mixing each
+//! input sample with an `IQOsc` phasor e<sup>-jωn</sup>, then low-pass filters and
+//! decimates the result with a `Decimator`, taking wideband IQ to a narrowband channel
+//! in one call. The oscillator and filter history carry over from one call to the
+//! next, so a stream can be processed in blocks of any size with the same result as
+//! processing it all at once.
+//!
+//! A `Decimator` runs a decimating FIR filter, which only computes the outputs that are
+//! kept. It's built from custom taps, a windowed-sinc low-pass design, or the response
+//! of a cascaded integrator-comb (CIC) filter. A hardware CIC filter runs its
+//! integrators on wrapping integers; with floating-point samples, the integrators
+//! would instead grow without bound and lose precision, so the equivalent FIR taps,
+//! which are integers before normalization, are used directly.
+//!
+//! This module requires the `std` feature.
+
+use core::f64::consts::PI;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use window::{Symmetry, Window};
+use {IQOsc, OscFloat};
+
+/// Decimating FIR filter for (sin, cos) samples.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Decimator<T: OscFloat> {
+    /// Holds the taps in reverse order, h(L - 1), ..., h(0).
+    taps: Vec<T>,
+    /// Holds the last L samples twice over, so they're always contiguous.
+    history: Vec<(T, T)>,
+    /// Index of the oldest sample in `history`.
+    pos: usize,
+    /// Decimation factor R.
+    factor: usize,
+    /// Number of samples fed since the last output.
+    count: usize,
+}
+
+impl<T: OscFloat> Decimator<T> {
+    /// Create a new `Decimator` with the given FIR taps h(k) that keeps one of every
+    /// given number R of filtered samples.
+    ///
+    /// Panics if there are no taps or the decimation factor is zero.
+    pub fn new(taps: &[T], factor: usize) -> Self {
+        assert!(!taps.is_empty(), "no taps");
+        assert!(factor > 0, "zero decimation factor");
+
+        let zero = T::from_f64(0.0);
+
+        Decimator {
+            taps: taps.iter().rev().cloned().collect(),
+            history: vec![(zero, zero); 2 * taps.len()],
+            pos: 0,
+            factor,
+            count: 0,
+        }
+    }
+
+    /// Create a new `Decimator` by the given factor R with a Blackman-windowed sinc
+    /// low-pass filter of the given length.
+    ///
+    /// The cutoff is at the output Nyquist frequency, 0.5/R cycles per input sample,
+    /// and the transition band is about 5.5/L cycles per input sample wide, centered
+    /// on the cutoff, with at least 74 dB of stopband rejection. The gain at DC is 1.
+    pub fn lowpass(factor: usize, len: usize) -> Self {
+        assert!(factor > 0, "zero decimation factor");
+
+        let taps: Vec<T> = lowpass_taps(0.5 / factor as f64, len).into_iter()
+            .map(|h| T::from_f64(h))
+            .collect();
+
+        Self::new(&taps, factor)
+    }
+
+    /// Create a new `Decimator` by the given factor R with the response of a CIC filter
+    /// with the given number of stages N and a differential delay of 1.
+    ///
+    /// The response is (Σ z<sup>-k</sup>)<sup>N</sup>, k = 0, ..., R - 1, with nulls at
+    /// every multiple of 1/R cycles per input sample, which fall on the frequencies
+    /// that alias to DC. It's scaled by 1/R<sup>N</sup> for a gain of 1 at DC.
+    pub fn cic(factor: usize, stages: usize) -> Self {
+        assert!(factor > 0, "zero decimation factor");
+
+        let gain = (factor as f64).powi(stages as i32);
+        let taps: Vec<T> = cic_taps(factor, stages).iter()
+            .map(|&h| T::from_f64(h / gain))
+            .collect();
+
+        Self::new(&taps, factor)
+    }
+
+    /// Retrieve the decimation factor R.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Retrieve the FIR taps h(k) in their original order.
+    pub fn taps(&self) -> Vec<T> {
+        self.taps.iter().rev().cloned().collect()
+    }
+
+    /// Feed the given (sin, cos) sample, returning the next filtered output after every
+    /// R samples.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<(T, T)> {
+        let len = self.taps.len();
+
+        self.history[self.pos] = sample;
+        self.history[self.pos + len] = sample;
+        self.pos = (self.pos + 1) % len;
+        self.count += 1;
+
+        if self.count < self.factor {
+            return None;
+        }
+
+        self.count = 0;
+
+        let window = &self.history[self.pos..self.pos + len];
+        let zero = T::from_f64(0.0);
+
+        Some(window.iter().zip(&self.taps).fold((zero, zero), |acc, (x, &h)| {
+            (acc.0 + h * x.0, acc.1 + h * x.1)
+        }))
+    }
+
+    /// Clear the filter history and restart the decimation phase.
+    pub fn reset(&mut self) {
+        let zero = T::from_f64(0.0);
+
+        for s in &mut self.history {
+            *s = (zero, zero);
+        }
+
+        self.pos = 0;
+        self.count = 0;
+    }
+}
+
+/// Digital down-converter combining a mixing oscillator and a `Decimator`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ddc<T: OscFloat> {
+    /// Generates e<sup>-jωn</sup> for the carrier at ω.
+    osc: IQOsc<T>,
+    /// Filters and decimates the mixed samples.
+    decim: Decimator<T>,
+}
+
+impl<T: OscFloat> Ddc<T> {
+    /// Create a new `Ddc` that shifts the carrier with the given phase step ω (in
+    /// radians per input sample) down to 0, then filters and decimates with the given
+    /// decimator.
+    pub fn new(step: T, decim: Decimator<T>) -> Self {
+        let zero = T::from_f64(0.0);
+
+        Ddc {
+            osc: IQOsc::new(zero, zero - step),
+            decim,
+        }
+    }
+
+    /// Change the carrier to the given phase step ω (in radians per input sample),
+    /// continuing from the current mixing phase.
+    pub fn set_step(&mut self, step: T) {
+        self.osc.set_step(T::from_f64(0.0) - step);
+    }
+
+    /// Retrieve the decimator.
+    pub fn decimator(&self) -> &Decimator<T> {
+        &self.decim
+    }
+
+    /// Feed the given (sin, cos) input sample, returning the next output sample after
+    /// every R input samples.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<(T, T)> {
+        // Multiply the input sample i + jq by the oscillator phasor cos + j sin.
+        let (q, i) = sample;
+        let (sin, cos) = self.osc.next();
+
+        self.decim.feed((i * sin + q * cos, i * cos - q * sin))
+    }
+
+    /// Process the given block of (sin, cos) input samples, appending the output
+    /// samples to the given buffer, and return the number appended.
+    pub fn process(&mut self, input: &[(T, T)], out: &mut Vec<(T, T)>) -> usize {
+        let start = out.len();
+        out.extend(input.iter().filter_map(|&s| self.feed(s)));
+        out.len() - start
+    }
+}
+
+/// Compute the unscaled taps of a CIC filter with the given factor R and number of
+/// stages N by convolving N boxcars of length R, keeping the integer taps exact.
+pub(crate) fn cic_taps(factor: usize, stages: usize) -> Vec<f64> {
+    let mut taps = vec![1.0];
+
+    for _ in 0..stages {
+        let mut next = vec![0.0; taps.len() + factor - 1];
+
+        for (i, &h) in taps.iter().enumerate() {
+            for out in &mut next[i..i + factor] {
+                *out += h;
+            }
+        }
+
+        taps = next;
+    }
+
+    taps
+}
+
+/// Design the taps of a Blackman-windowed sinc low-pass filter of the given length
+/// with the given cutoff (in cycles per sample) and a gain of 1 at DC.
+pub(crate) fn lowpass_taps(cutoff: f64, len: usize) -> Vec<f64> {
+    let window: Vec<f64> = Window::Blackman.generate(len, Symmetry::Symmetric);
+    let center = (len as f64 - 1.0) / 2.0;
+
+    let taps: Vec<f64> = window.iter().enumerate().map(|(k, &w)| {
+        let x = k as f64 - center;
+
+        if x == 0.0 {
+            w * 2.0 * cutoff
+        } else {
+            w * (2.0 * PI * cutoff * x).sin() / (PI * x)
+        }
+    }).collect();
+
+    let gain: f64 = taps.iter().sum();
+    taps.iter().map(|&h| h / gain).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dd::DoubleDouble;
+    use goertzel::Correlator;
+    use testutil::tone;
+
+    /// Measure the amplitude of the given frequency (in cycles per sample) in the
+    /// given samples.
+    fn amplitude(x: &[(f64, f64)], freq: f64) -> f64 {
+        let mut c = Correlator::new(2.0 * PI * freq, x.len());
+        x.iter().filter_map(|&s| c.feed(s)).last().unwrap().amplitude()
+    }
+
+    /// Measure the RMS level of the given samples.
+    fn rms(x: &[(f64, f64)]) -> f64 {
+        (x.iter().map(|&(s, c)| s * s + c * c).sum::<f64>() / x.len() as f64).sqrt()
+    }
+
+    /// Down-convert the given samples from the given carrier (in cycles per sample),
+    /// decimating by 8, and drop the filter's startup transient.
+    fn convert(x: &[(f64, f64)], carrier: f64) -> Vec<(f64, f64)> {
+        let mut ddc = Ddc::new(2.0 * PI * carrier, Decimator::lowpass(8, 255));
+        let mut out = vec![];
+        ddc.process(x, &mut out);
+        out.split_off(32)
+    }
+
+    #[test]
+    fn test_passband() {
+        // Tones up to 80% of the output Nyquist frequency come through flat.
+        let carrier = 0.21;
+
+        for i in -8..9 {
+            let offset = 0.05 * i as f64 / 8.0;
+            let out = convert(&tone(carrier + offset, 16_384), carrier);
+            let amp = amplitude(&out, 8.0 * offset);
+
+            assert!((20.0 * amp.log10()).abs() < 0.01, "{} {}", offset, amp);
+        }
+    }
+
+    #[test]
+    fn test_rejection() {
+        let carrier = 0.21;
+
+        // The image of the carrier and everything that would alias into the output
+        // band are rejected.
+        for &freq in &[-carrier + 0.01, carrier + 0.01 + 0.125, carrier - 0.2,
+                       carrier + 0.075, carrier - 0.075, 0.0] {
+            let out = convert(&tone(freq, 16_384), carrier);
+            assert!(20.0 * rms(&out).log10() < -70.0, "{}", freq);
+        }
+    }
+
+    #[test]
+    fn test_blocks() {
+        // Processing in uneven blocks matches processing in one call.
+        let x = tone(0.13, 5000);
+        let mut whole = Ddc::new(0.7, Decimator::cic(5, 3));
+        let mut expected = vec![];
+        whole.process(&x, &mut expected);
+
+        let mut ddc = Ddc::new(0.7, Decimator::cic(5, 3));
+        let mut out = vec![];
+        let mut start = 0;
+
+        for len in (1..).map(|n| n * 7 % 61) {
+            let end = (start + len).min(x.len());
+            let n = ddc.process(&x[start..end], &mut out);
+            assert!(n <= (end - start) / 5 + 1);
+
+            start = end;
+
+            if start == x.len() {
+                break;
+            }
+        }
+
+        assert_eq!(expected.len(), 1000);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_double_double() {
+        // A double-double down-converter agrees with the f64 one.
+        let x = tone(0.13, 500);
+        let mut ddc = Ddc::new(0.7, Decimator::cic(5, 3));
+        let mut expected = vec![];
+        ddc.process(&x, &mut expected);
+
+        let x: Vec<_> = x.iter()
+            .map(|&(s, c)| (DoubleDouble::from(s), DoubleDouble::from(c)))
+            .collect();
+        let mut ddc = Ddc::new(DoubleDouble::from(0.7), Decimator::cic(5, 3));
+        let mut out = vec![];
+        assert_eq!(ddc.process(&x, &mut out), 100);
+
+        for (a, b) in out.iter().zip(&expected) {
+            assert!((a.0.to_f64() - b.0).abs() < 1.0e-12);
+            assert!((a.1.to_f64() - b.1).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn test_cic() {
+        let d = Decimator::<f64>::cic(4, 3);
+        assert_eq!(d.factor(), 4);
+
+        let taps: Vec<f64> = d.taps().iter().map(|h| h * 64.0).collect();
+        assert_eq!(taps, vec![1.0, 3.0, 6.0, 10.0, 12.0, 12.0, 10.0, 6.0, 3.0, 1.0]);
+
+        // Tones at multiples of 1/R are nulled, and DC passes with unit gain.
+        for &(freq, amp) in &[(0.0, 1.0), (0.25, 0.0), (0.5, 0.0), (-0.25, 0.0)] {
+            let mut d = Decimator::cic(4, 3);
+            let out: Vec<(f64, f64)> = tone(freq, 400).into_iter()
+                .filter_map(|s| d.feed(s))
+                .skip(3)
+                .collect();
+
+            assert_eq!(out.len(), 97);
+            assert!((rms(&out) - amp).abs() < 1.0e-12, "{}", freq);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut d = Decimator::new(&[0.5f32, 0.5], 2);
+
+        assert_eq!(d.feed((1.0, 2.0)), None);
+        assert_eq!(d.feed((3.0, 4.0)), Some((2.0, 3.0)));
+        assert_eq!(d.feed((5.0, 6.0)), None);
+
+        d.reset();
+        assert_eq!(d.feed((1.0, 2.0)), None);
+        assert_eq!(d.feed((1.0, 2.0)), Some((1.0, 2.0)));
+    }
+}
@@ -0,0 +1,215 @@
+//! Frequency-locked loop for coarse carrier acquisition.
+//!
+//! A `pll::Pll` with a narrow loop bandwidth only pulls in frequency offsets within
+//! about its bandwidth. A `Fll` instead measures the frequency error directly, so it
+//! can pull in offsets up to nearly the Nyquist frequency, after which its frequency
+//! estimate can seed a PLL or Costas loop for fine tracking.
+//!
+//! At each sample, the input is multiplied by the conjugate of the phasor of an
+//! `IQOsc` NCO, giving the derotated sample d(n). The cross-product discriminator
+//!
+//! > e = Im(d(n - 1)<sup>*</sup>d(n)) / |d(n - 1)||d(n)| = sin Δω
+//!
+//! then measures the residual frequency Δω, with the sign of Δω for any offset
+//! within ±π radians per sample, and the first-order loop corrects the NCO by
+//! ω ← ω + ge for the loop gain g. Small corrections are applied with
+//! `IQOsc::nudge_step()` to avoid trig calls, and the NCO step is clamped to a
+//! configurable limit, which bounds the offsets the loop can pull in and keeps noise
+//! from driving it away.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use IQOsc;
+
+/// First-order frequency-locked loop with a cross-product discriminator.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fll<T: Float> {
+    /// Numerically controlled oscillator.
+    nco: IQOsc<T>,
+    /// Current phase step ω of the NCO.
+    freq: T,
+    /// Loop gain g.
+    gain: T,
+    /// Largest allowed |ω|.
+    limit: T,
+    /// Last derotated sample d(n - 1) as (re, im).
+    prev: (T, T),
+    /// Discriminator output e of the last sample.
+    error: T,
+}
+
+impl<T: Float> Fll<T> {
+    /// Create a new `Fll` starting at the given phase step ω (in radians per sample)
+    /// with the given loop gain g, and limited to steps within the given limit (in
+    /// radians per sample.)
+    ///
+    /// The frequency error decays by about a factor of 1 - g each sample, so a gain of
+    /// 0.01 settles in several hundred samples. A limit of π allows the full
+    /// frequency range, and a limit of 2πf limits it to a fraction f of the sample
+    /// rate.
+    pub fn new(step: T, gain: T, limit: T) -> Self {
+        let step = step.max(-limit).min(limit);
+
+        Fll {
+            nco: IQOsc::new(T::zero(), step),
+            freq: step,
+            gain,
+            limit,
+            prev: (T::zero(), T::zero()),
+            error: T::zero(),
+        }
+    }
+
+    /// Change the loop gain g.
+    pub fn set_gain(&mut self, gain: T) {
+        self.gain = gain;
+    }
+
+    /// Change the limit of the phase step (in radians per sample), clamping the
+    /// current step to it.
+    pub fn set_limit(&mut self, limit: T) {
+        self.limit = limit;
+        self.set_freq(self.freq);
+    }
+
+    /// Retrieve the current frequency estimate, the phase step ω of the NCO (in radians
+    /// per sample.)
+    pub fn freq(&self) -> T {
+        self.freq
+    }
+
+    /// Retrieve the discriminator output e of the last sample, the sine of the
+    /// residual frequency.
+    pub fn error(&self) -> T {
+        self.error
+    }
+
+    /// Feed the given (sin, cos) sample and update the loop, returning the sample with
+    /// the estimated frequency removed, as (sin, cos).
+    pub fn feed(&mut self, sample: (T, T)) -> (T, T) {
+        // Multiply the sample i + jq by the conjugate phasor cos - j sin.
+        let (q, i) = sample;
+        let (sin, cos) = self.nco.next();
+        let (re, im) = (i * cos + q * sin, q * cos - i * sin);
+
+        let (pre, pim) = self.prev;
+        let norm = (pre * pre + pim * pim).sqrt() * re.hypot(im);
+
+        self.error = if norm > T::zero() {
+            (pre * im - pim * re) / norm
+        } else {
+            T::zero()
+        };
+
+        self.prev = (re, im);
+
+        let next = (self.freq + self.gain * self.error).max(-self.limit).min(self.limit);
+        self.nco.nudge_step(next - self.freq);
+        self.freq = next;
+
+        (im, re)
+    }
+
+    /// Set the NCO step exactly to the given step clamped to the limit.
+    fn set_freq(&mut self, step: T) {
+        self.freq = step.max(-self.limit).min(self.limit);
+        self.nco.set_step(self.freq);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use pll::Pll;
+    use testutil::Rng;
+
+    #[test]
+    fn test_pull_in() {
+        // Pull in offsets up to 40% of the sample rate with the limit at 45%.
+        for &freq in &[0.001, -0.02, 0.1, -0.25, 0.33, 0.4, -0.4] {
+            let step = 2.0 * PI * freq;
+            let mut tone = IQOsc::new(0.7, step);
+            let mut fll = Fll::new(0.0, 0.05, 2.0 * PI * 0.45);
+
+            for _ in 0..3000 {
+                let (sin, cos) = tone.next();
+                fll.feed((3.0 * sin, 3.0 * cos));
+            }
+
+            assert!((fll.freq() - step).abs() < 1.0e-9, "{} {}", freq, fll.freq());
+            assert!(fll.error().abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_limit() {
+        // Offsets beyond the limit pin the loop at the limit.
+        let limit = 2.0 * PI * 0.2;
+        let mut tone = IQOsc::new(0.0f32, (2.0 * PI * 0.3) as f32);
+        let mut fll = Fll::new(0.0, 0.1, limit as f32);
+
+        for _ in 0..2000 {
+            fll.feed(tone.next());
+            assert!(fll.freq() as f64 <= limit * (1.0 + 1.0e-6));
+        }
+
+        assert!((fll.freq() as f64 - limit).abs() < 1.0e-6);
+
+        // Lowering the limit clamps the current step.
+        fll.set_limit(0.5);
+        assert_eq!(fll.freq(), 0.5);
+        assert_eq!(Fll::new(-3.0f64, 0.1, 1.0).freq(), -1.0);
+    }
+
+    #[test]
+    fn test_handoff() {
+        // Acquire a large offset at 10 dB SNR, then hand off to a narrow PLL that
+        // couldn't have pulled it in alone.
+        let step = 2.0 * PI * 0.137;
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        let mut tone = IQOsc::new(-2.0, step);
+        let mut fll = Fll::new(0.0, 0.01, PI);
+        let mut sum = 0.0;
+
+        for n in 0..20_000 {
+            let (sin, cos) = tone.next();
+            let (nsin, ncos) = rng.noise(0.5f64.sqrt() * 0.316);
+            fll.feed((sin + nsin, cos + ncos));
+
+            if n >= 10_000 {
+                sum += fll.freq();
+            }
+
+            if n == 12_000 {
+                fll.set_gain(0.001);
+            }
+        }
+
+        let estimate = sum / 10_000.0;
+        assert!((estimate - step).abs() < 1.0e-3, "{}", estimate - step);
+
+        let mut pll = Pll::new(estimate, 0.005, 0.707);
+
+        for _ in 0..5000 {
+            let (sin, cos) = tone.next();
+            let (nsin, ncos) = rng.noise(0.5f64.sqrt() * 0.316);
+            pll.feed((sin + nsin, cos + ncos));
+        }
+
+        assert!(pll.is_locked());
+        assert!((pll.freq() - step).abs() < 1.0e-3);
+
+        let mut pll = Pll::new(0.0, 0.005, 0.707);
+
+        for _ in 0..5000 {
+            pll.feed(tone.next());
+        }
+
+        assert!(!pll.is_locked());
+    }
+}
@@ -0,0 +1,425 @@
+//! Single-bin tone detection.
+//!
+//! Detecting a tone at a known frequency ω is the mirror image of generating it: the
+//! samples x(n) are correlated against the tone over a block of N samples,
+//!
+//! > C = Σ x(n)e<sup>-jωn</sup>, n = 0, ..., N - 1,
+//!
+//! giving a single DFT bin, which need not lie on the grid of an N-point DFT. For a
+//! tone Ae<sup>j(θ + ωn)</sup>, C = NAe<sup>jθ</sup>, so the magnitude and phase of C
+//! give the amplitude and initial phase of the tone.
+//!
+//! The `Goertzel` detector evaluates C with the Goertzel recurrence, which costs a
+//! single real multiply per input component per sample. The `Correlator` detector
+//! instead multiplies each sample by the conjugate of an `IQOsc` reference, which
+//! costs a complex multiply per sample but accumulates less roundoff error for long
+//! blocks at low frequencies.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use IQOsc;
+
+/// Correlation of a block of samples with a tone.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bin<T> {
+    /// Real part of the correlation C.
+    pub re: T,
+    /// Imaginary part of the correlation C.
+    pub im: T,
+    /// Number of samples N correlated.
+    pub samples: usize,
+}
+
+impl<T: Float> Bin<T> {
+    /// Compute the magnitude |C|.
+    pub fn magnitude(&self) -> T {
+        self.re.hypot(self.im)
+    }
+
+    /// Compute the power |C|².
+    pub fn power(&self) -> T {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Estimate the amplitude |C| / N of a tone at the detector frequency.
+    pub fn amplitude(&self) -> T {
+        self.magnitude() / T::from(self.samples.max(1)).unwrap()
+    }
+
+    /// Compute the phase of C (in radians), which is the phase of a tone at the
+    /// detector frequency at the first sample of the block.
+    pub fn phase(&self) -> T {
+        self.im.atan2(self.re)
+    }
+}
+
+/// Goertzel detector for a tone with phase step ω.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Goertzel<T: Float> {
+    /// Phase step ω.
+    step: T,
+    /// Holds (sin ω, cos ω).
+    phasor: (T, T),
+    /// Number of samples N in each block.
+    len: usize,
+    /// Number of samples fed in the current block.
+    count: usize,
+    /// Holds the complex state s(n - 1).
+    s1: (T, T),
+    /// Holds the complex state s(n - 2).
+    s2: (T, T),
+}
+
+impl<T: Float> Goertzel<T> {
+    /// Create a new `Goertzel` detector for the given phase step ω (in radians) over
+    /// blocks of the given number of samples.
+    ///
+    /// Panics if the block length is zero.
+    pub fn new(step: T, len: usize) -> Self {
+        assert!(len > 0, "empty block");
+
+        let zero = T::zero();
+
+        Goertzel {
+            step,
+            phasor: step.sin_cos(),
+            len,
+            count: 0,
+            s1: (zero, zero),
+            s2: (zero, zero),
+        }
+    }
+
+    /// Retrieve the phase step ω.
+    pub fn step(&self) -> T {
+        self.step
+    }
+
+    /// Retrieve the number of samples in each block.
+    pub fn block_len(&self) -> usize {
+        self.len
+    }
+
+    /// Feed the given (sin, cos) sample, and return the correlation of the block if it
+    /// completes the block.
+    ///
+    /// The detector is then reset for the next block.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<Bin<T>> {
+        let coef = self.phasor.1 + self.phasor.1;
+        let (sin, cos) = sample;
+
+        let s0 = (
+            cos + coef * self.s1.0 - self.s2.0,
+            sin + coef * self.s1.1 - self.s2.1,
+        );
+
+        self.s2 = self.s1;
+        self.s1 = s0;
+        self.count += 1;
+
+        if self.count < self.len {
+            return None;
+        }
+
+        let bin = self.result();
+        self.reset();
+
+        Some(bin)
+    }
+
+    /// Compute the correlation of the samples fed so far in the current block.
+    pub fn result(&self) -> Bin<T> {
+        let (sin, cos) = self.phasor;
+        let coef = cos + cos;
+
+        // Run one more step with zero input, so s(N) - e^(-jω)s(N - 1) gives
+        // e^(jωN)C.
+        let s0 = (coef * self.s1.0 - self.s2.0, coef * self.s1.1 - self.s2.1);
+        let y = (
+            s0.0 - (self.s1.0 * cos + self.s1.1 * sin),
+            s0.1 - (self.s1.1 * cos - self.s1.0 * sin),
+        );
+
+        // Remove the phase accumulated over the block.
+        let (rsin, rcos) = (self.step * T::from(self.count).unwrap()).sin_cos();
+
+        Bin {
+            re: y.0 * rcos + y.1 * rsin,
+            im: y.1 * rcos - y.0 * rsin,
+            samples: self.count,
+        }
+    }
+
+    /// Discard the samples fed so far in the current block.
+    pub fn reset(&mut self) {
+        let zero = T::zero();
+
+        self.count = 0;
+        self.s1 = (zero, zero);
+        self.s2 = (zero, zero);
+    }
+}
+
+/// Complex correlator for a tone with phase step ω, using an `IQOsc` as the reference.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Correlator<T: Float> {
+    /// Generates the reference e<sup>jωn</sup>.
+    osc: IQOsc<T>,
+    /// Number of samples N in each block.
+    len: usize,
+    /// Number of samples fed in the current block.
+    count: usize,
+    /// Holds the accumulated correlation.
+    acc: (T, T),
+}
+
+impl<T: Float> Correlator<T> {
+    /// Create a new `Correlator` for the given phase step ω (in radians) over blocks
+    /// of the given number of samples.
+    ///
+    /// Panics if the block length is zero.
+    pub fn new(step: T, len: usize) -> Self {
+        assert!(len > 0, "empty block");
+
+        Correlator {
+            osc: IQOsc::new(T::zero(), step),
+            len,
+            count: 0,
+            acc: (T::zero(), T::zero()),
+        }
+    }
+
+    /// Retrieve the number of samples in each block.
+    pub fn block_len(&self) -> usize {
+        self.len
+    }
+
+    /// Feed the given (sin, cos) sample, and return the correlation of the block if it
+    /// completes the block.
+    ///
+    /// The detector is then reset for the next block.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<Bin<T>> {
+        let (sin, cos) = sample;
+        let (rsin, rcos) = self.osc.next();
+
+        // Multiply by the conjugate reference e^(-jωn).
+        self.acc = (
+            self.acc.0 + cos * rcos + sin * rsin,
+            self.acc.1 + sin * rcos - cos * rsin,
+        );
+        self.count += 1;
+
+        if self.count < self.len {
+            return None;
+        }
+
+        let bin = self.result();
+        self.reset();
+
+        Some(bin)
+    }
+
+    /// Retrieve the correlation of the samples fed so far in the current block.
+    pub fn result(&self) -> Bin<T> {
+        Bin {
+            re: self.acc.0,
+            im: self.acc.1,
+            samples: self.count,
+        }
+    }
+
+    /// Discard the samples fed so far in the current block.
+    pub fn reset(&mut self) {
+        self.osc.set_phase(T::zero());
+        self.count = 0;
+        self.acc = (T::zero(), T::zero());
+    }
+}
+
+/// Bank of `Goertzel` detectors sharing the same blocks, for detecting several
+/// frequencies at once.
+///
+/// This requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GoertzelBank<T: Float> {
+    /// Detector for each frequency.
+    detectors: Vec<Goertzel<T>>,
+    /// Correlations of the last completed block.
+    results: Vec<Bin<T>>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Float> GoertzelBank<T> {
+    /// Create a new `GoertzelBank` with a detector for each of the given phase steps
+    /// (in radians) over blocks of the given number of samples.
+    ///
+    /// Panics if the block length is zero.
+    pub fn new(steps: &[T], len: usize) -> Self {
+        GoertzelBank {
+            detectors: steps.iter().map(|&s| Goertzel::new(s, len)).collect(),
+            results: Vec::with_capacity(steps.len()),
+        }
+    }
+
+    /// Feed the given (sin, cos) sample to every detector, and return the correlation
+    /// for each frequency, in the order given, if it completes the block.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<&[Bin<T>]> {
+        self.results.clear();
+
+        for d in self.detectors.iter_mut() {
+            if let Some(bin) = d.feed(sample) {
+                self.results.push(bin);
+            }
+        }
+
+        if self.results.is_empty() {
+            None
+        } else {
+            Some(&self.results)
+        }
+    }
+
+    /// Compute the correlation for each frequency of the samples fed so far in the
+    /// current block.
+    pub fn results(&self) -> Vec<Bin<T>> {
+        self.detectors.iter().map(|d| d.result()).collect()
+    }
+
+    /// Find the index of the frequency with the most power in the given block
+    /// results, or `None` if no frequency's power exceeds the given threshold.
+    pub fn strongest(bins: &[Bin<T>], threshold: T) -> Option<usize> {
+        bins.iter().enumerate()
+            .filter(|&(_, b)| b.power() > threshold)
+            .fold(None, |best: Option<(usize, T)>, (k, b)| match best {
+                Some((_, p)) if p >= b.power() => best,
+                _ => Some((k, b.power())),
+            })
+            .map(|(k, _)| k)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_goertzel() {
+        let step = 2.0 * PI * 0.1234;
+        let mut osc = IQOsc::new(0.7, step);
+        let mut g = Goertzel::new(step, 1000);
+        assert_eq!(g.block_len(), 1000);
+        assert_eq!(g.step(), step);
+
+        for _ in 0..999 {
+            assert!(g.feed(osc.next()).is_none());
+        }
+
+        assert_eq!(g.result().samples, 999);
+
+        let bin = g.feed(osc.next()).unwrap();
+        assert_eq!(bin.samples, 1000);
+        assert!((bin.amplitude() - 1.0).abs() < 1.0e-10);
+        assert!((bin.phase() - 0.7).abs() < 1.0e-10);
+
+        // The next block starts at phase 0.7 + 1000ω.
+        let (sin, cos) = (0.7 + 1000.0 * step).sin_cos();
+        let bin = (0..1000).filter_map(|_| g.feed(osc.next())).next().unwrap();
+        assert!((bin.re / 1000.0 - cos).abs() < 1.0e-9);
+        assert!((bin.im / 1000.0 - sin).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_correlator() {
+        let step = -2.0 * PI * 0.3;
+        let mut osc = IQOsc::new(-2.0, step);
+        let mut c = Correlator::new(step, 500);
+        let mut g = Goertzel::new(step, 500);
+        assert_eq!(c.block_len(), 500);
+
+        for _ in 0..499 {
+            let s = osc.next();
+            assert!(c.feed(s).is_none());
+            assert!(g.feed(s).is_none());
+        }
+
+        let s = osc.next();
+        let cb = c.feed(s).unwrap();
+        let gb = g.feed(s).unwrap();
+
+        assert!((cb.amplitude() - 1.0).abs() < 1.0e-12);
+        assert!((cb.phase() + 2.0).abs() < 1.0e-12);
+        assert!((cb.re - gb.re).abs() < 1.0e-9);
+        assert!((cb.im - gb.im).abs() < 1.0e-9);
+
+        c.feed((1.0, 2.0));
+        c.reset();
+        assert_eq!(c.result(), Bin { re: 0.0, im: 0.0, samples: 0 });
+    }
+
+    #[test]
+    fn test_rejection() {
+        // A tone on another bin of the block is rejected entirely.
+        let n = 256;
+        let bin = |k: f64| (2.0 * PI * k / n as f64) as f32;
+        let mut osc = IQOsc::new(0.0f32, bin(20.0));
+        let mut g = Goertzel::new(bin(21.0), n);
+        let mut c = Correlator::new(bin(21.0), n);
+
+        let (gb, cb) = (0..n).fold((None, None), |_, _| {
+            let s = osc.next();
+            (g.feed(s), c.feed(s))
+        });
+
+        assert!(gb.unwrap().amplitude() < 1.0e-4);
+        assert!(cb.unwrap().amplitude() < 1.0e-4);
+
+        // Real tones split between positive and negative frequencies.
+        let mut g = Goertzel::new(bin(-20.0), n);
+        let mut osc = IQOsc::new(0.0f32, bin(20.0));
+        let bin = (0..n).filter_map(|_| g.feed((0.0, osc.next().1))).next().unwrap();
+        assert!((bin.amplitude() - 0.5).abs() < 1.0e-4);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_bank() {
+        let steps: Vec<f64> = [697.0, 770.0, 852.0, 941.0].iter()
+            .map(|f| 2.0 * PI * f / 8000.0)
+            .collect();
+        let mut bank = GoertzelBank::new(&steps, 205);
+
+        // Mix of the 770 Hz and 941 Hz tones, at different levels.
+        let mut a = IQOsc::new(0.0, steps[1]);
+        let mut b = IQOsc::new(1.0, steps[3]);
+
+        for n in 0..410 {
+            let (asin, acos) = a.next();
+            let (bsin, bcos) = b.next();
+
+            match bank.feed((asin + 0.5 * bsin, acos + 0.5 * bcos)) {
+                Some(bins) => {
+                    assert_eq!(n % 205, 204);
+                    assert_eq!(bins.len(), 4);
+                    assert!((bins[1].amplitude() - 1.0).abs() < 0.05);
+                    assert!((bins[3].amplitude() - 0.5).abs() < 0.05);
+                    assert!(bins[0].amplitude() < 0.1);
+                    assert!(bins[2].amplitude() < 0.1);
+                    assert_eq!(GoertzelBank::strongest(bins, 100.0), Some(1));
+                    assert_eq!(GoertzelBank::strongest(bins, 1.0e6), None);
+                },
+                None => assert!(n % 205 != 204),
+            }
+        }
+
+        assert!(bank.results().iter().all(|b| b.samples == 0));
+    }
+}
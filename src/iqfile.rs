@@ -0,0 +1,484 @@
+//! Raw I/Q sample files in common SDR formats.
+//!
+//! Each sample is stored as an interleaved I (cos) and Q (sin) pair in little-endian
+//! byte order, as used by GNU Radio, inspectrum, and SDR hardware tools. Samples are
+//! passed to and from this module as (sin, cos) pairs to match `IQOsc::next()`, with
+//! full scale at ±1.0.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use num_traits::Float;
+
+/// Sample format of a raw I/Q file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Interleaved 32-bit floats.
+    Cf32,
+    /// Interleaved 64-bit floats.
+    Cf64,
+    /// Interleaved signed 16-bit integers.
+    Cs16,
+    /// Interleaved signed 8-bit integers, as used by HackRF.
+    Cs8,
+    /// Interleaved unsigned 8-bit integers with an offset of 127.5, as used by RTL-SDR.
+    Cu8,
+}
+
+impl Format {
+    /// Number of bytes in each I or Q component.
+    pub fn component_size(&self) -> usize {
+        match *self {
+            Format::Cf32 => 4,
+            Format::Cf64 => 8,
+            Format::Cs16 => 2,
+            Format::Cs8 | Format::Cu8 => 1,
+        }
+    }
+
+    /// Number of bytes in each I/Q sample.
+    pub fn sample_size(&self) -> usize {
+        2 * self.component_size()
+    }
+
+    /// Check if the format stores floating-point components.
+    pub fn is_float(&self) -> bool {
+        matches!(*self, Format::Cf32 | Format::Cf64)
+    }
+
+    /// Retrieve the conventional file extension/name of the format.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Format::Cf32 => "cf32",
+            Format::Cf64 => "cf64",
+            Format::Cs16 => "cs16",
+            Format::Cs8 => "cs8",
+            Format::Cu8 => "cu8",
+        }
+    }
+
+    /// Scale from full scale ±1.0 to integer codes.
+    fn full_scale(&self) -> f64 {
+        match *self {
+            Format::Cf32 | Format::Cf64 => 1.0,
+            Format::Cs16 => 32767.0,
+            Format::Cs8 => 127.0,
+            Format::Cu8 => 127.5,
+        }
+    }
+
+    /// Encode the given component, already scaled and rounded for integer formats, into
+    /// the given buffer.
+    fn encode(&self, x: f64, buf: &mut Vec<u8>) {
+        match *self {
+            Format::Cf32 => buf.extend_from_slice(&(x as f32).to_le_bytes()),
+            Format::Cf64 => buf.extend_from_slice(&x.to_le_bytes()),
+            Format::Cs16 => buf.extend_from_slice(&(x as i16).to_le_bytes()),
+            Format::Cs8 => buf.push(x as i8 as u8),
+            Format::Cu8 => buf.push((x + 127.5) as u8),
+        }
+    }
+
+    /// Decode the component at the start of the given buffer to full scale ±1.0.
+    fn decode(&self, buf: &[u8]) -> f64 {
+        let mut b8 = [0; 8];
+        b8[..self.component_size()].copy_from_slice(&buf[..self.component_size()]);
+
+        match *self {
+            Format::Cf32 => f32::from_le_bytes([b8[0], b8[1], b8[2], b8[3]]) as f64,
+            Format::Cf64 => f64::from_le_bytes(b8),
+            Format::Cs16 => i16::from_le_bytes([b8[0], b8[1]]) as f64 / 32767.0,
+            Format::Cs8 => b8[0] as i8 as f64 / 127.0,
+            Format::Cu8 => (b8[0] as f64 - 127.5) / 127.5,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, ParseFormatError> {
+        match s {
+            "cf32" | "fc32" => Ok(Format::Cf32),
+            "cf64" | "fc64" => Ok(Format::Cf64),
+            "cs16" | "sc16" => Ok(Format::Cs16),
+            "cs8" | "sc8" => Ok(Format::Cs8),
+            "cu8" | "uc8" => Ok(Format::Cu8),
+            _ => Err(ParseFormatError { name: s.to_string() }),
+        }
+    }
+}
+
+/// Error from parsing an unknown format name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseFormatError {
+    /// Name that failed to parse.
+    name: String,
+}
+
+impl ParseFormatError {
+    /// Retrieve the name that failed to parse.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown I/Q format '{}'", self.name)
+    }
+}
+
+impl Error for ParseFormatError {}
+
+/// Dithering applied before quantizing to integer formats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// Round to the nearest code.
+    Off,
+    /// Add triangular-PDF noise of ±1 LSB before rounding, which decorrelates the
+    /// quantization error from the signal at the cost of a slightly higher noise floor.
+    Triangular,
+}
+
+/// Writes I/Q samples to a raw file in the given format.
+///
+/// Writes go directly to the underlying writer, so it should typically be buffered.
+pub struct IQWriter<W: Write> {
+    /// Underlying writer.
+    inner: W,
+    /// Output format.
+    format: Format,
+    /// Gain applied to each component before quantization.
+    gain: f64,
+    /// Whether float components beyond ±1.0 are clipped.
+    clip: bool,
+    /// Dithering for integer formats.
+    dither: Dither,
+    /// State of the dither noise generator.
+    rng: u64,
+    /// Number of components clipped so far.
+    clipped: u64,
+    /// Holds encoded bytes before they're written.
+    buf: Vec<u8>,
+}
+
+impl<W: Write> IQWriter<W> {
+    /// Create a new `IQWriter` over the given writer in the given format, with unity
+    /// gain and no dithering.
+    pub fn new(inner: W, format: Format) -> Self {
+        IQWriter {
+            inner,
+            format,
+            gain: 1.0,
+            clip: false,
+            dither: Dither::Off,
+            rng: 0x9e3779b97f4a7c15,
+            clipped: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Set the gain applied to each component before quantization.
+    pub fn set_gain(&mut self, gain: f64) {
+        self.gain = gain;
+    }
+
+    /// Set whether components of float formats are clipped to ±1.0.
+    ///
+    /// Integer formats always saturate at their most extreme codes.
+    pub fn set_clip(&mut self, clip: bool) {
+        self.clip = clip;
+    }
+
+    /// Set the dithering applied to integer formats.
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    /// Retrieve the number of I or Q components that have been clipped or saturated.
+    pub fn clipped(&self) -> u64 {
+        self.clipped
+    }
+
+    /// Write the given (sin, cos) sample.
+    pub fn write_sample<T: Float>(&mut self, sample: (T, T)) -> io::Result<()> {
+        self.write_samples(&[sample])
+    }
+
+    /// Write the given (sin, cos) samples.
+    pub fn write_samples<T: Float>(&mut self, samples: &[(T, T)]) -> io::Result<()> {
+        self.buf.clear();
+
+        for &(sin, cos) in samples {
+            let i = self.quantize(cos.to_f64().unwrap());
+            let q = self.quantize(sin.to_f64().unwrap());
+
+            self.format.encode(i, &mut self.buf);
+            self.format.encode(q, &mut self.buf);
+        }
+
+        self.inner.write_all(&self.buf)
+    }
+
+    /// Write the given number of samples from an oscillator, where the `next` closure
+    /// returns (sin, cos) at each step, for example `|| osc.next()` for an `IQOsc`.
+    pub fn write_osc<T, F>(&mut self, samples: usize, mut next: F) -> io::Result<()>
+        where T: Float, F: FnMut() -> (T, T)
+    {
+        const BLOCK: usize = 4096;
+
+        let mut block = Vec::with_capacity(BLOCK.min(samples));
+        let mut left = samples;
+
+        while left > 0 {
+            let n = left.min(BLOCK);
+
+            block.clear();
+            block.extend((0..n).map(|_| next()));
+            self.write_samples(&block)?;
+
+            left -= n;
+        }
+
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Retrieve the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Scale, dither, and clip the given component.
+    fn quantize(&mut self, x: f64) -> f64 {
+        let scale = self.format.full_scale();
+        let x = x * self.gain * scale;
+
+        if self.format.is_float() {
+            return if self.clip && x.abs() > 1.0 {
+                self.clipped += 1;
+                x.signum()
+            } else {
+                x
+            };
+        }
+
+        let x = match self.dither {
+            Dither::Off => x,
+            Dither::Triangular => x + self.uniform() - self.uniform(),
+        };
+
+        // Round to the nearest code, which for the offset-binary format lies halfway
+        // between integers.
+        let x = if self.format == Format::Cu8 {
+            (x + 127.5).round() - 127.5
+        } else {
+            x.round()
+        };
+
+        if x.abs() > scale {
+            self.clipped += 1;
+            x.signum() * scale
+        } else {
+            x
+        }
+    }
+
+    /// Generate a uniform random number in [0, 1) using xorshift64*.
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+
+        (self.rng.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Reads I/Q samples from a raw file in the given format.
+///
+/// Each sample is filled by calling `read()` on the underlying reader until it's
+/// complete, retrying on `Interrupted`, so the reader should typically be buffered.
+pub struct IQReader<R: Read> {
+    /// Underlying reader.
+    inner: R,
+    /// Input format.
+    format: Format,
+    /// Holds the raw bytes of each sample.
+    buf: Vec<u8>,
+}
+
+impl<R: Read> IQReader<R> {
+    /// Create a new `IQReader` over the given reader in the given format.
+    pub fn new(inner: R, format: Format) -> Self {
+        IQReader {
+            inner,
+            format,
+            buf: vec![0; format.sample_size()],
+        }
+    }
+
+    /// Read the next (sin, cos) sample, scaled to full scale ±1.0, or `None` at the end
+    /// of the stream.
+    ///
+    /// A partial sample at the end of the stream causes an `UnexpectedEof` error.
+    pub fn read_sample<T: Float>(&mut self) -> io::Result<Option<(T, T)>> {
+        let mut filled = 0;
+
+        while filled < self.buf.len() {
+            match self.inner.read(&mut self.buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                   "partial I/Q sample")),
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        let size = self.format.component_size();
+        let i = self.format.decode(&self.buf);
+        let q = self.format.decode(&self.buf[size..]);
+
+        Ok(Some((T::from(q).unwrap(), T::from(i).unwrap())))
+    }
+
+    /// Read samples into the given buffer until it's full or the stream ends, and
+    /// return the number of samples read.
+    pub fn read_samples<T: Float>(&mut self, buf: &mut [(T, T)]) -> io::Result<usize> {
+        for (n, out) in buf.iter_mut().enumerate() {
+            match self.read_sample()? {
+                Some(s) => *out = s,
+                None => return Ok(n),
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Retrieve the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use IQOsc;
+
+    fn round_trip(format: Format, tol: f64) {
+        let mut w = IQWriter::new(vec![], format);
+        let mut o = IQOsc::new(0.3, 0.05);
+        w.write_osc(1000, || o.next()).unwrap();
+        assert_eq!(w.clipped(), 0);
+
+        let bytes = w.into_inner();
+        assert_eq!(bytes.len(), 1000 * format.sample_size());
+
+        let mut r = IQReader::new(&bytes[..], format);
+        let mut o = IQOsc::new(0.3, 0.05);
+        let mut n = 0;
+
+        while let Some((sin, cos)) = r.read_sample::<f64>().unwrap() {
+            let (esin, ecos) = o.next();
+            assert!((sin - esin).abs() <= tol, "{} {}", sin, esin);
+            assert!((cos - ecos).abs() <= tol, "{} {}", cos, ecos);
+            n += 1;
+        }
+
+        assert_eq!(n, 1000);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(Format::Cf64, 0.0);
+        round_trip(Format::Cf32, 1.0e-7);
+        round_trip(Format::Cs16, 0.5 / 32767.0);
+        round_trip(Format::Cs8, 0.5 / 127.0);
+        round_trip(Format::Cu8, 0.5 / 127.5);
+    }
+
+    #[test]
+    fn test_encoding() {
+        let mut w = IQWriter::new(vec![], Format::Cs16);
+        w.write_sample((-1.0, 1.0)).unwrap();
+        assert_eq!(w.into_inner(), vec![0xff, 0x7f, 0x01, 0x80]);
+
+        let mut w = IQWriter::new(vec![], Format::Cu8);
+        w.write_samples(&[(-1.0, 1.0), (0.0, 0.0)]).unwrap();
+        assert_eq!(w.into_inner(), vec![255, 0, 128, 128]);
+
+        let mut w = IQWriter::new(vec![], Format::Cf32);
+        w.write_sample((0.5f32, -2.0f32)).unwrap();
+        assert_eq!(w.into_inner(), vec![0, 0, 0, 0xc0, 0, 0, 0, 0x3f]);
+    }
+
+    #[test]
+    fn test_clip() {
+        let mut w = IQWriter::new(vec![], Format::Cs8);
+        w.set_gain(2.0);
+        w.write_samples(&[(0.4, 0.6), (-0.1, -0.9)]).unwrap();
+        assert_eq!(w.clipped(), 2);
+        assert_eq!(w.into_inner(), vec![127, 102, 129, 231]);
+
+        let mut w = IQWriter::new(vec![], Format::Cf64);
+        w.set_gain(2.0);
+        w.write_sample((0.4, 0.6)).unwrap();
+        assert_eq!(w.clipped(), 0);
+        w.set_clip(true);
+        w.write_sample((0.4, 0.6)).unwrap();
+        assert_eq!(w.clipped(), 1);
+
+        let bytes = w.into_inner();
+        let mut r = IQReader::new(&bytes[..], Format::Cf64);
+        assert_eq!(r.read_sample().unwrap(), Some((0.8, 1.2)));
+        assert_eq!(r.read_sample().unwrap(), Some((0.8, 1.0)));
+        assert_eq!(r.read_sample::<f64>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_dither() {
+        // A constant between codes should average to its true value with dithering.
+        let mut w = IQWriter::new(vec![], Format::Cs8);
+        w.set_dither(Dither::Triangular);
+        w.write_samples(&vec![(0.3 / 127.0, -10.25 / 127.0); 10000]).unwrap();
+
+        let bytes = w.into_inner();
+        let mut r = IQReader::new(&bytes[..], Format::Cs8);
+        let mut buf = vec![(0.0, 0.0); 20000];
+        assert_eq!(r.read_samples(&mut buf).unwrap(), 10000);
+
+        let (sin, cos) = buf[..10000].iter()
+            .fold((0.0, 0.0), |(s, c), &(sin, cos)| (s + sin, c + cos));
+
+        assert!((sin / 10000.0 * 127.0 - 0.3).abs() < 0.03);
+        assert!((cos / 10000.0 * 127.0 - -10.25).abs() < 0.03);
+        assert!(buf[..10000].iter().all(|&(sin, _)| (sin * 127.0).abs() <= 2.0));
+    }
+
+    #[test]
+    fn test_partial() {
+        let bytes = [1, 2, 3];
+        let mut r = IQReader::new(&bytes[..], Format::Cs16);
+        let err = r.read_sample::<f32>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("cf32".parse(), Ok(Format::Cf32));
+        assert_eq!("cu8".parse(), Ok(Format::Cu8));
+        assert_eq!(Format::Cs16.name(), "cs16");
+
+        let err = "cs12".parse::<Format>().unwrap_err();
+        assert_eq!(err.name(), "cs12");
+        assert_eq!(err.to_string(), "unknown I/Q format 'cs12'");
+    }
+}
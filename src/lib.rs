@@ -61,21 +61,178 @@
 //! test bench_trig32 ... bench:     490,407 ns/iter (+/- 90,148)
 //! test bench_trig64 ... bench:   2,365,592 ns/iter (+/- 148,062)
 //! ```
+//!
+//! When even `IQOsc<f64>` isn't accurate enough, such as when producing a reference to
+//! measure the drift of other oscillators against, `IQOsc<dd::DoubleDouble>` runs the
+//! same recurrence with roughly 106 bits of precision, at a much higher cost per step.
+//!
+//! ## Complex Samples
+//!
+//! Modules that process complex baseband signals pass each sample as a (sin, cos)
+//! pair, matching the output of `IQOsc::next()`, and take it as the complex sample
+//! cos + j sin.
+//!
+//! ## `no_std`
+//!
+//! The crate links `std` through the default `std` feature. With default features
+//! disabled, it can be used on targets without `std`, and the trig functions called at
+//! initialization are computed with `libm`.
+//!
+//! ## Serialization
+//!
+//! The optional `serde` feature implements `Serialize` and `Deserialize` for the
+//! oscillator and measurement state, so long runs can be checkpointed and resumed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(all(test, not(feature = "std")))]
+#[macro_use]
+extern crate std;
 
 extern crate num_traits;
 
-use num_traits::Float;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "sigmf")]
+extern crate serde_json;
+
+use core::fmt;
+use core::ops::{Add, Div, Mul, Sub};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod bank;
+pub mod chirp;
+pub mod costas;
+#[cfg(feature = "std")]
+pub mod czt;
+pub mod dd;
+#[cfg(feature = "std")]
+pub mod ddc;
+#[cfg(feature = "std")]
+pub mod duc;
+pub mod fll;
+pub mod goertzel;
+#[cfg(feature = "std")]
+pub mod iqfile;
+#[cfg(feature = "std")]
+pub mod multitone;
+pub mod pll;
+#[cfg(feature = "std")]
+pub mod response;
+#[cfg(feature = "sigmf")]
+pub mod sigmf;
+#[cfg(feature = "std")]
+pub mod sdft;
+#[cfg(feature = "std")]
+pub mod spectrum;
+#[cfg(test)]
+mod testutil;
+#[cfg(feature = "std")]
+pub mod twiddle;
+#[cfg(feature = "std")]
+pub mod window;
+#[cfg(feature = "std")]
+pub mod wav;
+#[cfg(feature = "std")]
+pub mod xlating;
+
+/// Numeric operations needed to initialize and step an `IQOsc`.
+///
+/// This is implemented for every `num_traits::Float` type and additionally for
+/// `dd::DoubleDouble`, which can be used as a high-precision reference.
+///
+/// The oscillators, banks, and up/down converters only need these operations, so
+/// they run on any `OscFloat`. The detectors, tracking loops, and analysis tools
+/// also need functions like `atan2()`, `hypot()`, and `sqrt()`, so they're bound on
+/// `num_traits::Float` and limited to `f32` and `f64`.
+pub trait OscFloat:
+    Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> +
+    Div<Output = Self>
+{
+    /// Compute (sin x, cos x) for the given x (in radians).
+    fn sin_cos(self) -> (Self, Self);
+
+    /// Check if the value is neither infinite nor NaN.
+    fn is_finite(self) -> bool;
+
+    /// Compute the absolute value.
+    fn abs(self) -> Self;
+
+    /// Retrieve the value nearest π.
+    fn pi() -> Self;
+
+    /// Convert from the given `f64`, rounding if necessary.
+    fn from_f64(x: f64) -> Self;
+}
+
+impl<T: num_traits::Float> OscFloat for T {
+    fn sin_cos(self) -> (Self, Self) {
+        num_traits::Float::sin_cos(self)
+    }
+
+    fn is_finite(self) -> bool {
+        num_traits::Float::is_finite(self)
+    }
+
+    fn abs(self) -> Self {
+        num_traits::Float::abs(self)
+    }
+
+    fn pi() -> Self {
+        T::from(core::f64::consts::PI).unwrap()
+    }
+
+    fn from_f64(x: f64) -> Self {
+        T::from(x).unwrap()
+    }
+}
+
+/// Errors caused by invalid oscillator parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OscError {
+    /// The phase was infinite or NaN.
+    NonFinitePhase,
+    /// The phase step was infinite or NaN.
+    NonFiniteStep,
+    /// The phase step exceeded π radians per sample, so it would alias to a lower
+    /// frequency.
+    StepBeyondNyquist,
+}
+
+impl fmt::Display for OscError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            OscError::NonFinitePhase => "phase is not finite",
+            OscError::NonFiniteStep => "phase step is not finite",
+            OscError::StepBeyondNyquist => "phase step exceeds the Nyquist frequency",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OscError {}
 
 /// Quadrature oscillator with current phase Φ(t) and phase step ω.
+///
+/// With the `serde` feature, the oscillator state can be serialized to checkpoint a
+/// long-running signal, and a deserialized oscillator continues bit-identically.
 #[derive(Copy, Clone)]
-pub struct IQOsc<T: Float> {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IQOsc<T: OscFloat> {
     /// Holds (sin ω, cos ω) for the phase step ω.
     step: (T, T),
     /// Holds (sin Φ(t), cos Φ(t)) for the current phase Φ(t).
     phase: (T, T),
 }
 
-impl<T: Float> IQOsc<T> {
+impl<T: OscFloat> IQOsc<T> {
     /// Create a new `IQOsc` starting at the given initial phase θ<sub>0</sub> (in
     /// radians) and with the given phase step ω (in radians).
     ///
@@ -88,17 +245,84 @@ impl<T: Float> IQOsc<T> {
         }
     }
 
+    /// Create a new `IQOsc` as with `new()`, but fail if the initial phase or phase
+    /// step isn't finite.
+    pub fn try_new(phase: T, step: T) -> Result<Self, OscError> {
+        Ok(IQOsc {
+            step: check_step(step)?.sin_cos(),
+            phase: check_phase(phase)?.sin_cos(),
+        })
+    }
+
+    /// Create a new `IQOsc` as with `try_new()`, but also fail if the phase step is
+    /// beyond the Nyquist frequency, |ω| > π.
+    pub fn try_new_strict(phase: T, step: T) -> Result<Self, OscError> {
+        Ok(IQOsc {
+            step: check_nyquist(check_step(step)?)?.sin_cos(),
+            phase: check_phase(phase)?.sin_cos(),
+        })
+    }
+
     /// Change the phase step to the given ω (in radians).
     pub fn set_step(&mut self, step: T) {
         self.step = step.sin_cos();
     }
 
+    /// Change the phase step as with `set_step()`, but fail and leave the step
+    /// unchanged if it isn't finite.
+    pub fn try_set_step(&mut self, step: T) -> Result<(), OscError> {
+        self.step = check_step(step)?.sin_cos();
+        Ok(())
+    }
+
+    /// Change the phase step as with `try_set_step()`, but also fail if the step is
+    /// beyond the Nyquist frequency, |ω| > π.
+    pub fn try_set_step_strict(&mut self, step: T) -> Result<(), OscError> {
+        self.step = check_nyquist(check_step(step)?)?.sin_cos();
+        Ok(())
+    }
+
     /// Change the current phase to the given θ (in radians).
     pub fn set_phase(&mut self, phase: T) {
         self.phase = phase.sin_cos();
     }
 
+    /// Change the current phase as with `set_phase()`, but fail and leave the phase
+    /// unchanged if it isn't finite.
+    pub fn try_set_phase(&mut self, phase: T) -> Result<(), OscError> {
+        self.phase = check_phase(phase)?.sin_cos();
+        Ok(())
+    }
+
+    /// Advance the current phase by the given small angle δ (in radians) without any
+    /// trig calls.
+    ///
+    /// The rotation by δ is approximated with a Taylor series, accurate to about
+    /// δ<sup>6</sup>/720, which suits a tracking loop that nudges its oscillator by a
+    /// small correction every sample. Larger corrections, above 1/64 radians, fall back
+    /// to an exact rotation with a trig call. The phase phasor is also renormalized
+    /// toward unit magnitude, so repeated nudges don't accumulate amplitude error.
+    pub fn nudge_phase(&mut self, delta: T) {
+        self.phase = rotate_small(self.phase, delta);
+    }
+
+    /// Change the phase step by the given small angle δ (in radians) without any trig
+    /// calls, with the same approximation as `nudge_phase()`.
+    pub fn nudge_step(&mut self, delta: T) {
+        self.step = rotate_small(self.step, delta);
+    }
+
+    /// Check if the current phase and phase step are finite.
+    ///
+    /// Once either becomes infinite or NaN, every following call to `next()` returns
+    /// NaN, so long-running pipelines can poll this to fail fast.
+    pub fn is_finite(&self) -> bool {
+        self.phase.0.is_finite() && self.phase.1.is_finite() &&
+            self.step.0.is_finite() && self.step.1.is_finite()
+    }
+
     /// Step the phase to Φ(t+1) and return (sin Φ(t), cos Φ(t)).
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> (T, T) {
         let cur = self.phase;
 
@@ -113,7 +337,61 @@ impl<T: Float> IQOsc<T> {
     }
 }
 
+/// Largest angle (in radians) rotated by with a Taylor series rather than a trig call.
+const NUDGE_MAX: f64 = 1.0 / 64.0;
+
+/// Rotate the given (sin, cos) phasor by the given angle δ and renormalize it.
+fn rotate_small<T: OscFloat>((sin, cos): (T, T), delta: T) -> (T, T) {
+    let d2 = delta * delta;
+    let one = T::from_f64(1.0);
+
+    let (dsin, dcos) = if delta.abs() <= T::from_f64(NUDGE_MAX) {
+        // Compute sin δ ≈ δ - δ³/6 + δ⁵/120 and cos δ ≈ 1 - δ²/2 + δ⁴/24.
+        (
+            delta * (one - d2 / T::from_f64(6.0) * (one - d2 / T::from_f64(20.0))),
+            one - d2 / T::from_f64(2.0) * (one - d2 / T::from_f64(12.0)),
+        )
+    } else {
+        delta.sin_cos()
+    };
+
+    let (sin, cos) = (sin * dcos + cos * dsin, cos * dcos - sin * dsin);
+
+    // Scale by 1/|p| ≈ (3 - |p|²)/2, which is accurate when |p| is near 1.
+    let scale = (T::from_f64(3.0) - sin * sin - cos * cos) / T::from_f64(2.0);
+
+    (sin * scale, cos * scale)
+}
+
+/// Verify the given phase is finite.
+fn check_phase<T: OscFloat>(phase: T) -> Result<T, OscError> {
+    if phase.is_finite() {
+        Ok(phase)
+    } else {
+        Err(OscError::NonFinitePhase)
+    }
+}
+
+/// Verify the given phase step is finite.
+fn check_step<T: OscFloat>(step: T) -> Result<T, OscError> {
+    if step.is_finite() {
+        Ok(step)
+    } else {
+        Err(OscError::NonFiniteStep)
+    }
+}
+
+/// Verify the given phase step is at most π in magnitude.
+fn check_nyquist<T: OscFloat>(step: T) -> Result<T, OscError> {
+    if step.abs() <= T::pi() {
+        Ok(step)
+    } else {
+        Err(OscError::StepBeyondNyquist)
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::excessive_precision, clippy::approx_constant, clippy::unnecessary_cast)]
 mod test {
     use super::*;
     use std::f32::consts::PI as PI32;
@@ -482,6 +760,113 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_nudge() {
+        // Nudges match exact rotations.
+        let mut o = IQOsc::new(0.3f64, 0.1);
+        o.nudge_phase(0.05);
+        o.nudge_step(-0.02);
+
+        let (sin, cos) = o.next();
+        assert!((sin - 0.35f64.sin()).abs() < 1.0e-10);
+        assert!((cos - 0.35f64.cos()).abs() < 1.0e-10);
+
+        let (sin, cos) = o.next();
+        assert!((sin - 0.43f64.sin()).abs() < 1.0e-10);
+        assert!((cos - 0.43f64.cos()).abs() < 1.0e-10);
+
+        // Large nudges fall back to exact rotations.
+        let mut o = IQOsc::new(0.3f64, 0.1);
+        o.nudge_phase(4.0);
+        o.nudge_step(-2.5);
+        o.next();
+
+        let (sin, cos) = o.next();
+        assert!((sin - 1.9f64.sin()).abs() < 1.0e-12);
+        assert!((cos - 1.9f64.cos()).abs() < 1.0e-12);
+
+        // Renormalizing keeps the amplitude at 1 over many steps and nudges.
+        let mut o = IQOsc::new(0.0f32, 0.7);
+        let mut phase = 0.0f64;
+        let mut step = 0.7f64;
+
+        for n in 0..1_000_000 {
+            let delta = 0.01 * ((n % 7) as f64 - 3.0);
+            let (sin, cos) = o.next();
+            phase += step + delta;
+            step += 1.0e-3 * delta;
+            o.nudge_phase(delta as f32);
+            o.nudge_step(1.0e-3 * delta as f32);
+
+            if n % 100_000 == 0 {
+                assert!((sin * sin + cos * cos - 1.0).abs() < 1.0e-5);
+            }
+        }
+
+        let (sin, cos) = o.next();
+        assert!((sin * sin + cos * cos - 1.0).abs() < 1.0e-5);
+        assert!((phase as f32 - sin.atan2(cos)).sin().abs() < 0.1);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(IQOsc::try_new(0.0, 0.1).is_ok());
+        assert_eq!(IQOsc::try_new(f32::NAN, 0.1).err(), Some(OscError::NonFinitePhase));
+        assert_eq!(IQOsc::try_new(0.0, f64::INFINITY).err(),
+                   Some(OscError::NonFiniteStep));
+        assert!(IQOsc::try_new(0.0, 4.0).is_ok());
+        assert_eq!(IQOsc::try_new_strict(0.0, 4.0).err(),
+                   Some(OscError::StepBeyondNyquist));
+        assert_eq!(IQOsc::try_new_strict(0.0, -4.0).err(),
+                   Some(OscError::StepBeyondNyquist));
+        assert!(IQOsc::try_new_strict(0.0, PI64).is_ok());
+
+        let mut o = IQOsc::new(0.0, PI32 / 2.0);
+        assert!(o.is_finite());
+
+        assert_eq!(o.try_set_step(f32::NAN), Err(OscError::NonFiniteStep));
+        assert_eq!(o.try_set_step_strict(4.0), Err(OscError::StepBeyondNyquist));
+        assert_eq!(o.try_set_phase(f32::NEG_INFINITY), Err(OscError::NonFinitePhase));
+        assert!(o.is_finite());
+
+        let (sin, cos) = o.next();
+        assert!((sin - 0.0).abs() < 0.0001);
+        assert!((cos - 1.0).abs() < 0.0001);
+        let (sin, cos) = o.next();
+        assert!((sin - 1.0).abs() < 0.0001);
+        assert!((cos - 0.0).abs() < 0.0001);
+
+        assert_eq!(o.try_set_phase(PI32), Ok(()));
+        assert_eq!(o.try_set_step_strict(-PI32 / 2.0), Ok(()));
+
+        o.set_phase(f32::NAN);
+        assert!(!o.is_finite());
+        assert!(o.next().0.is_nan());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        extern crate bincode;
+
+        let mut o = IQOsc::new(0.1, PI64 / 7.0);
+
+        for _ in 0..1000 {
+            o.next();
+        }
+
+        let mut restored: IQOsc<f64> =
+            bincode::deserialize(&bincode::serialize(&o).unwrap()).unwrap();
+
+        for _ in 0..1000 {
+            let (sin, cos) = o.next();
+            let (rsin, rcos) = restored.next();
+
+            assert_eq!(sin.to_bits(), rsin.to_bits());
+            assert_eq!(cos.to_bits(), rcos.to_bits());
+        }
+    }
+
     #[test]
     fn test_mult() {
         let m = 120;
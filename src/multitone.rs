@@ -0,0 +1,310 @@
+//! Multi-tone signals from a bank of oscillators.
+//!
+//! A `MultiTone` sums the output of an `IQOsc` for each tone, each scaled by its own
+//! amplitude, as used for two-tone and multi-tone intermodulation testing.
+//!
+//! When many tones start in phase, their peaks line up and the crest factor (the ratio
+//! of peak to RMS amplitude) of the sum grows with the number of tones, which can drive
+//! a device under test into compression. The `Phases` presets instead spread the
+//! initial phases of evenly spaced tones to keep the crest factor low.
+//!
+//! This module requires the `std` feature.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use {IQOsc, OscFloat};
+
+/// Preset initial phases for the tones of a `MultiTone`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Phases {
+    /// All tones start at zero phase, which maximizes the crest factor.
+    Zero,
+    /// Schroeder phases, which give a low crest factor for evenly spaced tones of any
+    /// amplitudes.
+    ///
+    /// With relative powers p<sub>l</sub> summing to 1, the tone at index k has phase
+    /// -2π Σ<sub>l<k</sub> (k - l)p<sub>l</sub>.
+    Schroeder,
+    /// Newman phases πk²/N for the tone at index k of N, which give a low crest factor
+    /// for evenly spaced tones of equal amplitude.
+    Newman,
+}
+
+impl Phases {
+    /// Compute the phase (in radians) of each tone with the given amplitudes, where the
+    /// tones are in order of frequency.
+    pub fn compute<T: OscFloat>(&self, amps: &[T]) -> Vec<T> {
+        let zero = T::from_f64(0.0);
+        let n = T::from_f64(amps.len() as f64);
+
+        match *self {
+            Phases::Zero => vec![zero; amps.len()],
+            Phases::Schroeder => {
+                let total = amps.iter().fold(zero, |s, &a| s + a * a);
+                let tau = T::pi() + T::pi();
+
+                // An all-zero bank has no power to distribute.
+                if total <= zero {
+                    return vec![zero; amps.len()];
+                }
+
+                (0..amps.len()).map(|k| {
+                    let sum = amps[..k].iter().enumerate().fold(zero, |s, (l, &a)| {
+                        s + T::from_f64((k - l) as f64) * a * a / total
+                    });
+
+                    zero - tau * sum
+                }).collect()
+            },
+            Phases::Newman => (0..amps.len()).map(|k| {
+                let k = T::from_f64(k as f64);
+                T::pi() * k * k / n
+            }).collect(),
+        }
+    }
+}
+
+/// Quadrature oscillator producing the sum of several tones, each with its own phase
+/// step ω<sub>k</sub>, amplitude A<sub>k</sub>, and initial phase.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MultiTone<T: OscFloat> {
+    /// Oscillator for each tone.
+    oscs: Vec<IQOsc<T>>,
+    /// Amplitude of each tone.
+    amps: Vec<T>,
+}
+
+impl<T: OscFloat> MultiTone<T> {
+    /// Create a new `MultiTone` with no tones, which outputs zeros.
+    pub fn new() -> Self {
+        MultiTone {
+            oscs: vec![],
+            amps: vec![],
+        }
+    }
+
+    /// Create a new `MultiTone` with a tone at each of the given phase steps (in
+    /// radians) and amplitudes, starting at the given preset phases.
+    ///
+    /// Panics if the number of steps and amplitudes differ.
+    pub fn with_phases(steps: &[T], amps: &[T], phases: Phases) -> Self {
+        assert_eq!(steps.len(), amps.len(), "mismatched steps and amplitudes");
+
+        MultiTone {
+            oscs: steps.iter().zip(phases.compute(amps))
+                .map(|(&step, phase)| IQOsc::new(phase, step))
+                .collect(),
+            amps: amps.to_vec(),
+        }
+    }
+
+    /// Add a tone with the given phase step, amplitude, and initial phase (in radians),
+    /// and return its index.
+    pub fn add_tone(&mut self, step: T, amp: T, phase: T) -> usize {
+        self.oscs.push(IQOsc::new(phase, step));
+        self.amps.push(amp);
+        self.oscs.len() - 1
+    }
+
+    /// Retrieve the number of tones.
+    pub fn len(&self) -> usize {
+        self.oscs.len()
+    }
+
+    /// Check if there are no tones.
+    pub fn is_empty(&self) -> bool {
+        self.oscs.is_empty()
+    }
+
+    /// Retrieve the amplitude of the tone at the given index.
+    pub fn amplitude(&self, idx: usize) -> T {
+        self.amps[idx]
+    }
+
+    /// Change the phase step of the tone at the given index to the given ω (in
+    /// radians).
+    pub fn set_step(&mut self, idx: usize, step: T) {
+        self.oscs[idx].set_step(step);
+    }
+
+    /// Change the amplitude of the tone at the given index.
+    pub fn set_amplitude(&mut self, idx: usize, amp: T) {
+        self.amps[idx] = amp;
+    }
+
+    /// Change the current phase of the tone at the given index to the given θ (in
+    /// radians).
+    pub fn set_phase(&mut self, idx: usize, phase: T) {
+        self.oscs[idx].set_phase(phase);
+    }
+
+    /// Change the current phases of all tones to the given preset, assuming the tones
+    /// were added in order of frequency.
+    pub fn set_phases(&mut self, phases: Phases) {
+        for (osc, phase) in self.oscs.iter_mut().zip(phases.compute(&self.amps)) {
+            osc.set_phase(phase);
+        }
+    }
+
+    /// Step every tone and return the sum (Σ A<sub>k</sub> sin Φ<sub>k</sub>(t),
+    /// Σ A<sub>k</sub> cos Φ<sub>k</sub>(t)).
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> (T, T) {
+        let zero = T::from_f64(0.0);
+
+        self.oscs.iter_mut().zip(&self.amps).fold((zero, zero), |(s, c), (osc, &a)| {
+            let (sin, cos) = osc.next();
+            (s + a * sin, c + a * cos)
+        })
+    }
+
+    /// Fill the given buffer with consecutive (sin, cos) samples.
+    pub fn fill(&mut self, buf: &mut [(T, T)]) {
+        for s in buf.iter_mut() {
+            *s = self.next();
+        }
+    }
+}
+
+impl<T: OscFloat> Default for MultiTone<T> {
+    fn default() -> Self {
+        MultiTone::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use testutil::dft;
+
+    /// Compute the magnitude of each DFT bin, normalized by the length.
+    fn spectrum(samples: &[(f64, f64)]) -> Vec<f64> {
+        let len = samples.len() as f64;
+        dft(samples).iter().map(|&(re, im)| re.hypot(im) / len).collect()
+    }
+
+    fn crest(samples: &[(f64, f64)]) -> f64 {
+        let peak = samples.iter().fold(0.0f64, |m, s| m.max(s.1.abs()));
+        let rms = (samples.iter().map(|s| s.1 * s.1).sum::<f64>() /
+                   samples.len() as f64).sqrt();
+
+        peak / rms
+    }
+
+    #[test]
+    fn test_tones() {
+        const N: usize = 256;
+        let bin = |k: f64| 2.0 * PI * k / N as f64;
+
+        let mut m = MultiTone::new();
+        assert!(m.is_empty());
+        assert_eq!(m.add_tone(bin(10.0), 1.0, 0.0), 0);
+        assert_eq!(m.add_tone(bin(37.0), 0.5, 1.0), 1);
+        assert_eq!(m.add_tone(bin(-50.0), 0.25, -2.0), 2);
+        assert_eq!(m.len(), 3);
+
+        let mut buf = vec![(0.0, 0.0); N];
+        m.fill(&mut buf);
+
+        let mag = spectrum(&buf);
+
+        for (k, &m) in mag.iter().enumerate() {
+            let expected = match k {
+                10 => 1.0,
+                37 => 0.5,
+                206 => 0.25,
+                _ => 0.0,
+            };
+
+            assert!((m - expected).abs() < 1.0e-12, "{} {}", k, m);
+        }
+
+        // Retune and rescale while running.
+        m.set_step(1, bin(64.0));
+        m.set_amplitude(2, 0.0);
+        assert_eq!(m.amplitude(2), 0.0);
+        m.fill(&mut buf);
+
+        let mag = spectrum(&buf);
+        assert!((mag[10] - 1.0).abs() < 1.0e-12);
+        assert!((mag[64] - 0.5).abs() < 1.0e-12);
+        assert!(mag[37] < 1.0e-12);
+        assert!(mag[206] < 1.0e-12);
+    }
+
+    #[test]
+    fn test_phase() {
+        let mut m = MultiTone::new();
+        m.add_tone(0.1, 2.0, 0.5);
+        m.add_tone(0.2, 1.0, -1.0);
+
+        let (sin, cos) = m.next();
+        assert!((sin - (2.0 * 0.5f64.sin() + (-1.0f64).sin())).abs() < 1.0e-15);
+        assert!((cos - (2.0 * 0.5f64.cos() + (-1.0f64).cos())).abs() < 1.0e-15);
+
+        m.set_phase(0, 0.0);
+        m.set_phase(1, PI / 2.0);
+        let (sin, cos) = m.next();
+        assert!((sin - 1.0).abs() < 1.0e-15);
+        assert!((cos - 2.0).abs() < 1.0e-15);
+
+        assert_eq!(MultiTone::<f32>::new().next(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_presets() {
+        let amps = [1.0; 4];
+        assert_eq!(Phases::Zero.compute(&amps), vec![0.0; 4]);
+
+        // Schroeder phases for equal amplitudes are -πk(k+1)/N.
+        for (k, &p) in Phases::Schroeder.compute(&amps).iter().enumerate() {
+            let k = k as f64;
+            assert!((p + PI * k * (k + 1.0) / 4.0).abs() < 1.0e-12);
+        }
+
+        for (k, &p) in Phases::Newman.compute(&amps).iter().enumerate() {
+            assert!((p - PI * (k * k) as f64 / 4.0).abs() < 1.0e-12);
+        }
+
+        // All power in the first tone.
+        let p = Phases::Schroeder.compute(&[1.0f32, 0.0, 0.0]);
+        assert_eq!(p[0], 0.0);
+        assert!((p[1] + 2.0 * PI as f32).abs() < 1.0e-6);
+        assert!((p[2] + 4.0 * PI as f32).abs() < 1.0e-6);
+
+        assert_eq!(Phases::Schroeder.compute(&[0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_crest() {
+        const N: usize = 1024;
+        const TONES: usize = 32;
+
+        let steps: Vec<f64> = (1..TONES + 1).map(|k| 2.0 * PI * k as f64 / N as f64)
+            .collect();
+        let amps = vec![1.0; TONES];
+
+        let crests: Vec<f64> = [Phases::Zero, Phases::Schroeder, Phases::Newman].iter()
+            .map(|&p| {
+                let mut buf = vec![(0.0, 0.0); N];
+                MultiTone::with_phases(&steps, &amps, p).fill(&mut buf);
+                crest(&buf)
+            }).collect();
+
+        // In-phase tones peak at √(2·32) = 8.
+        assert!((crests[0] - 8.0).abs() < 1.0e-9);
+        assert!(crests[1] < 2.0, "{}", crests[1]);
+        assert!(crests[2] < 2.0, "{}", crests[2]);
+
+        // Reapplying presets matches a new bank.
+        let mut m = MultiTone::with_phases(&steps, &amps, Phases::Zero);
+        m.set_phases(Phases::Newman);
+        let mut buf = vec![(0.0, 0.0); N];
+        m.fill(&mut buf);
+        assert!((crest(&buf) - crests[2]).abs() < 1.0e-12);
+    }
+}
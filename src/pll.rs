@@ -0,0 +1,273 @@
+//! Phase-locked loop for carrier tracking.
+//!
+//! A `Pll` tracks the phase and frequency of a carrier with an `IQOsc` as its
+//! numerically controlled oscillator (NCO). At each sample, the input is multiplied by
+//! the conjugate of the NCO phasor, and the phase of the product is the phase error e.
+//! A second-order loop filter then corrects the NCO,
+//!
+//! > ω ← ω + βe, Φ ← Φ + ω + αe,
+//!
+//! with gains α and β chosen from the loop bandwidth B<sub>n</sub> and damping
+//! factor ζ,
+//!
+//! > α = 4ζB<sub>n</sub>/D, β = 4B<sub>n</sub><sup>2</sup>/D,
+//! > D = 1 + 2ζB<sub>n</sub> + B<sub>n</sub><sup>2</sup>.
+//!
+//! Setting the NCO phase and step with `IQOsc::set_phase()` and `IQOsc::set_step()`
+//! would cost two trig calls every sample, so the corrections, which are small once the
+//! loop is tracking, are applied with `IQOsc::nudge_phase()` and
+//! `IQOsc::nudge_step()` instead, which also keep the NCO at unit amplitude and only
+//! fall back to trig calls for the large corrections of a wide loop acquiring.
+//!
+//! Lock is detected by smoothing cos e, the in-phase component of the normalized error
+//! phasor, which approaches 1 when the loop is locked and averages near 0 when it
+//! isn't.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use IQOsc;
+
+/// Second-order phase-locked loop.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pll<T: Float> {
+    /// Numerically controlled oscillator, holding the phase for the next sample.
+    nco: IQOsc<T>,
+    /// Current phase step ω of the NCO.
+    freq: T,
+    /// Proportional gain α.
+    alpha: T,
+    /// Integral gain β.
+    beta: T,
+    /// Phase error e of the last sample.
+    error: T,
+    /// Smoothed cos e.
+    lock: T,
+    /// Smoothing factor of the lock detector.
+    lock_alpha: T,
+    /// Smoothed cos e above which the loop is considered locked.
+    threshold: T,
+}
+
+impl<T: Float> Pll<T> {
+    /// Create a new `Pll` starting at the given phase step ω (in radians per sample)
+    /// with the given loop bandwidth B<sub>n</sub> (in radians per sample) and damping
+    /// factor ζ.
+    ///
+    /// A damping factor of 1/√2 is a common choice. Wider loops acquire faster and
+    /// over a wider frequency offset but let through more noise.
+    ///
+    /// The lock detector initially smooths over about 200 samples with a threshold of
+    /// 0.8, which can be changed with `set_lock_detector()`.
+    pub fn new(step: T, bandwidth: T, damping: T) -> Self {
+        let mut pll = Pll {
+            nco: IQOsc::new(T::zero(), step),
+            freq: step,
+            alpha: T::zero(),
+            beta: T::zero(),
+            error: T::zero(),
+            lock: T::zero(),
+            lock_alpha: T::from(0.005).unwrap(),
+            threshold: T::from(0.8).unwrap(),
+        };
+
+        pll.set_bandwidth(bandwidth, damping);
+        pll
+    }
+
+    /// Change the loop bandwidth B<sub>n</sub> (in radians per sample) and damping
+    /// factor ζ, keeping the current phase and frequency.
+    pub fn set_bandwidth(&mut self, bandwidth: T, damping: T) {
+        let two = T::from(2.0).unwrap();
+        let four = T::from(4.0).unwrap();
+        let denom = T::one() + two * damping * bandwidth + bandwidth * bandwidth;
+
+        self.alpha = four * damping * bandwidth / denom;
+        self.beta = four * bandwidth * bandwidth / denom;
+    }
+
+    /// Change the lock detector to smooth cos e with the given factor, about the
+    /// reciprocal of the number of samples averaged, and report lock above the given
+    /// threshold.
+    pub fn set_lock_detector(&mut self, smoothing: T, threshold: T) {
+        self.lock_alpha = smoothing;
+        self.threshold = threshold;
+    }
+
+    /// Retrieve the current phase step ω of the NCO (in radians per sample.)
+    pub fn freq(&self) -> T {
+        self.freq
+    }
+
+    /// Retrieve the phase error e of the last sample (in radians.)
+    pub fn phase_error(&self) -> T {
+        self.error
+    }
+
+    /// Retrieve the smoothed cos e of the lock detector, in the range [-1, 1].
+    pub fn lock_metric(&self) -> T {
+        self.lock
+    }
+
+    /// Check if the loop is locked.
+    pub fn is_locked(&self) -> bool {
+        self.lock > self.threshold
+    }
+
+    /// Feed the given (sin, cos) sample and update the loop, returning the sample with
+    /// the tracked carrier removed, as (sin, cos).
+    pub fn feed(&mut self, sample: (T, T)) -> (T, T) {
+        let (re, im) = self.derotate(sample);
+        let mag = re.hypot(im);
+
+        self.correct(im.atan2(re), if mag > T::zero() { re / mag } else { T::zero() });
+
+        (im, re)
+    }
+
+    /// Step the NCO and multiply the given (sin, cos) sample by the conjugate of its
+    /// phasor, returning the product as (re, im).
+    pub(crate) fn derotate(&mut self, sample: (T, T)) -> (T, T) {
+        // Multiply the sample i + jq by the conjugate phasor cos - j sin.
+        let (q, i) = sample;
+        let (sin, cos) = self.nco.next();
+
+        (i * cos + q * sin, q * cos - i * sin)
+    }
+
+    /// Correct the NCO for the given phase error e of the last derotated sample, and
+    /// feed the given lock detector input, cos e, to the detector.
+    pub(crate) fn correct(&mut self, error: T, cos_err: T) {
+        self.error = error;
+        self.lock = self.lock + self.lock_alpha * (cos_err - self.lock);
+
+        // The NCO has already advanced by the old step, so the phase also picks up
+        // the change in step.
+        let dfreq = self.beta * error;
+        self.freq = self.freq + dfreq;
+        self.nco.nudge_step(dfreq);
+        self.nco.nudge_phase(dfreq + self.alpha * error);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use testutil::Rng;
+
+    #[test]
+    fn test_offset() {
+        // Acquire a tone 20% off the initial frequency.
+        let step = 2.0 * PI * 0.0123;
+        let mut tone = IQOsc::new(1.0, step);
+        let mut pll = Pll::new(0.8 * step, 0.02, 0.707);
+
+        for n in 0..5000 {
+            let (sin, cos) = tone.next();
+            let (q, i) = pll.feed((0.5 * sin, 0.5 * cos));
+
+            if n > 2000 {
+                assert!((pll.freq() - step).abs() < 1.0e-9);
+                assert!(pll.phase_error().abs() < 1.0e-9);
+                assert!((i - 0.5).abs() < 1.0e-9 && q.abs() < 1.0e-9);
+                assert!(pll.is_locked());
+            }
+        }
+    }
+
+    #[test]
+    fn test_noise() {
+        // Track a drifting tone at 10 dB SNR in single precision.
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        let mut pll = Pll::new(0.0f32, 0.01, 0.707);
+        let mut phase = 2.0;
+        let mut step = 0.05;
+        let mut sq = 0.0;
+
+        for n in 0..100_000 {
+            let (nsin, ncos) = rng.noise(0.5f64.sqrt() * 0.316);
+            let (sin, cos) = f64::sin_cos(phase);
+            phase += step;
+            step += 1.0e-7;
+
+            pll.feed(((sin + nsin) as f32, (cos + ncos) as f32));
+
+            if n >= 10_000 {
+                assert!(pll.is_locked(), "{}", n);
+                assert!((pll.freq() as f64 - step).abs() < 0.01);
+
+                let err = pll.phase_error() as f64;
+                sq += err * err;
+            }
+        }
+
+        // The phase error is dominated by the noise itself, about 0.22 radians RMS at
+        // 10 dB SNR.
+        let rms = (sq / 90_000.0).sqrt();
+        assert!(rms < 0.3, "{}", rms);
+    }
+
+    #[test]
+    fn test_wide() {
+        // Wide loops take corrections of radians per sample without diverging.
+        for &(bandwidth, damping) in &[(0.5, 1.0), (0.8, 0.707)] {
+            for &phase in &[3.0, -3.1, 1.5] {
+                let mut tone = IQOsc::new(phase, 0.2);
+                let mut pll = Pll::new(0.0, bandwidth, damping);
+
+                for _ in 0..2000 {
+                    let (q, i) = pll.feed(tone.next());
+                    assert!(q.is_finite() && i.is_finite() && pll.freq().is_finite());
+                }
+
+                assert!(pll.is_locked(), "{} {}", bandwidth, phase);
+                assert!((pll.freq() - 0.2).abs() < 1.0e-9);
+                assert!(pll.phase_error().abs() < 1.0e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unlocked() {
+        // Noise alone and a tone far outside the loop bandwidth don't lock.
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+        let mut pll = Pll::new(0.0, 0.005, 0.707);
+
+        for _ in 0..20_000 {
+            pll.feed(rng.noise(1.0));
+            assert!(!pll.is_locked());
+        }
+
+        let mut tone = IQOsc::new(0.0, 2.0 * PI * 0.3);
+        let mut pll = Pll::new(0.0, 0.005, 0.707);
+
+        for _ in 0..20_000 {
+            pll.feed(tone.next());
+            assert!(!pll.is_locked());
+        }
+
+        assert!(pll.lock_metric().abs() < 0.5);
+    }
+
+    #[test]
+    fn test_reacquire() {
+        // Follow a frequency hop and a phase jump.
+        let mut pll = Pll::new(0.1, 0.03, 1.0);
+        pll.set_lock_detector(0.01, 0.9);
+
+        for &(phase, step) in &[(0.0, 0.1), (1.5, 0.13), (-2.0, -0.05)] {
+            let mut tone = IQOsc::new(phase, step);
+
+            for _ in 0..3000 {
+                pll.feed(tone.next());
+            }
+
+            assert!(pll.is_locked());
+            assert!((pll.freq() - step).abs() < 1.0e-9);
+        }
+    }
+}
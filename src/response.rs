@@ -0,0 +1,219 @@
+//! Frequency response of FIR and IIR filters.
+//!
+//! The response of a filter with numerator coefficients b<sub>k</sub> and denominator
+//! coefficients a<sub>k</sub> at the normalized frequency ω is
+//!
+//! > H(e<sup>jω</sup>) = B(ω)/A(ω), with B(ω) = Σ b<sub>k</sub>e<sup>-jωk</sup>,
+//!
+//! and A(ω) likewise, where an FIR filter has A(ω) = 1. The kernel
+//! e<sup>-jωk</sup> for every coefficient is generated by an `IQOsc` stepping by -ω
+//! rather than with a trig call per coefficient.
+//!
+//! The group delay τ(ω) = -dφ/dω is found from the same sums, since for a polynomial
+//! P(ω) the delay is Re(Σ kp<sub>k</sub>e<sup>-jωk</sup> / P(ω)), and the delay of
+//! B/A is the delay of B minus that of A.
+//!
+//! Frequencies are given in cycles per sample, so 0.5 is the Nyquist frequency.
+//!
+//! This module requires the `std` feature.
+
+use core::f64::consts::PI;
+
+use num_traits::Float;
+
+use IQOsc;
+
+/// Response of a filter at a single frequency.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Response {
+    /// Frequency in cycles per sample.
+    pub freq: f64,
+    /// Real part of H(e<sup>jω</sup>).
+    pub re: f64,
+    /// Imaginary part of H(e<sup>jω</sup>).
+    pub im: f64,
+    /// Group delay in samples, which is not finite at zeros of the response.
+    pub group_delay: f64,
+}
+
+impl Response {
+    /// Compute the magnitude |H|.
+    pub fn magnitude(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Compute the magnitude in decibels, 20 log<sub>10</sub> |H|.
+    pub fn db(&self) -> f64 {
+        10.0 * (self.re * self.re + self.im * self.im).log10()
+    }
+
+    /// Compute the phase of H (in radians) in the range [-π, π].
+    pub fn phase(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+}
+
+/// Build a grid of the given number of frequencies evenly spaced from the given start
+/// to the given stop frequency (in cycles per sample), including both ends.
+pub fn grid(start: f64, stop: f64, points: usize) -> Vec<f64> {
+    match points {
+        0 => vec![],
+        1 => vec![start],
+        _ => (0..points).map(|i| {
+            start + (stop - start) * i as f64 / (points - 1) as f64
+        }).collect(),
+    }
+}
+
+/// Evaluate the response of the FIR filter with the given taps at each of the given
+/// frequencies (in cycles per sample.)
+pub fn fir<T: Float>(taps: &[T], freqs: &[f64]) -> Vec<Response> {
+    iir(taps, &[T::one()], freqs)
+}
+
+/// Evaluate the response of the IIR filter with the given numerator coefficients b and
+/// denominator coefficients a at each of the given frequencies (in cycles per
+/// sample.)
+///
+/// The coefficients needn't be normalized so that a<sub>0</sub> = 1.
+pub fn iir<T: Float>(b: &[T], a: &[T], freqs: &[f64]) -> Vec<Response> {
+    let b: Vec<f64> = b.iter().map(|c| c.to_f64().unwrap()).collect();
+    let a: Vec<f64> = a.iter().map(|c| c.to_f64().unwrap()).collect();
+
+    freqs.iter().map(|&freq| {
+        let omega = 2.0 * PI * freq;
+        let (num, num_delay) = eval(&b, omega);
+        let (den, den_delay) = eval(&a, omega);
+
+        // Divide B/A.
+        let norm = den.0 * den.0 + den.1 * den.1;
+
+        Response {
+            freq,
+            re: (num.0 * den.0 + num.1 * den.1) / norm,
+            im: (num.1 * den.0 - num.0 * den.1) / norm,
+            group_delay: num_delay - den_delay,
+        }
+    }).collect()
+}
+
+/// Unwrap the phases of the given responses, removing jumps of more than π between
+/// successive frequencies.
+pub fn unwrapped_phase(responses: &[Response]) -> Vec<f64> {
+    let mut offset = 0.0;
+    let mut prev: Option<f64> = None;
+
+    responses.iter().map(|r| {
+        let phase = r.phase();
+
+        if let Some(p) = prev {
+            let jump = phase + offset - p;
+            offset -= 2.0 * PI * (jump / (2.0 * PI)).round();
+        }
+
+        let phase = phase + offset;
+        prev = Some(phase);
+        phase
+    }).collect()
+}
+
+/// Evaluate P(ω) = Σ p<sub>k</sub>e<sup>-jωk</sup> as (re, im), along with the group
+/// delay of P at ω.
+fn eval(coefs: &[f64], omega: f64) -> ((f64, f64), f64) {
+    let mut osc = IQOsc::new(0.0, -omega);
+    let mut sum = (0.0, 0.0);
+    let mut deriv = (0.0, 0.0);
+
+    for (k, &c) in coefs.iter().enumerate() {
+        let (sin, cos) = osc.next();
+        sum = (sum.0 + c * cos, sum.1 + c * sin);
+        deriv = (deriv.0 + k as f64 * c * cos, deriv.1 + k as f64 * c * sin);
+    }
+
+    let delay = (deriv.0 * sum.0 + deriv.1 * sum.1) / (sum.0 * sum.0 + sum.1 * sum.1);
+
+    (sum, delay)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fir() {
+        // Compare a windowed-sinc lowpass against direct trig.
+        let taps: Vec<f64> = (0..101).map(|k| {
+            let x = k as f64 - 50.0;
+            let sinc = if x == 0.0 { 0.2 } else { (0.2 * PI * x).sin() / (PI * x) };
+            sinc * (0.54 - 0.46 * (2.0 * PI * k as f64 / 100.0).cos())
+        }).collect();
+
+        let freqs = grid(0.0, 0.5, 2001);
+        let resp = fir(&taps, &freqs);
+        assert_eq!(resp.len(), 2001);
+
+        for r in &resp {
+            let (re, im) = taps.iter().enumerate().fold((0.0, 0.0), |acc, (k, &h)| {
+                let (sin, cos) = (-2.0 * PI * r.freq * k as f64).sin_cos();
+                (acc.0 + h * cos, acc.1 + h * sin)
+            });
+
+            assert!((r.re - re).abs() < 1.0e-12);
+            assert!((r.im - im).abs() < 1.0e-12);
+
+            // A symmetric filter has a constant group delay of half its length.
+            if r.db() > -40.0 {
+                assert!((r.group_delay - 50.0).abs() < 1.0e-6, "{}", r.freq);
+            }
+        }
+
+        assert!(resp[0].db().abs() < 0.1);
+        assert!(resp.iter().filter(|r| r.freq > 0.15).all(|r| r.db() < -50.0));
+    }
+
+    #[test]
+    fn test_iir() {
+        // One-pole lowpass y(n) = (1 - p)x(n) + py(n - 1).
+        let p = 0.9f32;
+        let freqs = grid(0.0, 0.5, 101);
+        let resp = iir(&[1.0 - p], &[1.0, -p], &freqs);
+
+        for r in &resp {
+            let p = p as f64;
+            let (sin, cos) = (-2.0 * PI * r.freq).sin_cos();
+            let den = (1.0 - p * cos, -p * sin);
+            let norm = den.0 * den.0 + den.1 * den.1;
+            let mag2 = (1.0 - p) * (1.0 - p) / norm;
+
+            assert!((r.magnitude() - mag2.sqrt()).abs() < 1.0e-12);
+
+            // τ = (p cos ω - p²) / (1 - 2p cos ω + p²).
+            let delay = (p * cos - p * p) / norm;
+            assert!((r.group_delay - delay).abs() < 1.0e-9, "{}", r.freq);
+        }
+
+        assert!(resp[0].db().abs() < 1.0e-9);
+        assert!((resp[0].group_delay - 9.0).abs() < 1.0e-5);
+
+        // Scaling every coefficient doesn't change the response.
+        let scaled = iir(&[2.0 * (1.0 - p)], &[2.0, -2.0 * p], &freqs);
+        assert!((scaled[50].re - resp[50].re).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn test_phase() {
+        // A pure delay of 3 samples has linear phase -3ω.
+        let freqs = grid(0.0, 0.49, 50);
+        let resp = fir(&[0.0, 0.0, 0.0, 1.0], &freqs);
+        let phase = unwrapped_phase(&resp);
+
+        for (r, &phase) in resp.iter().zip(&phase) {
+            assert!((phase + 3.0 * 2.0 * PI * r.freq).abs() < 1.0e-9);
+            assert!((r.group_delay - 3.0).abs() < 1.0e-9);
+            assert!(r.db().abs() < 1.0e-12);
+        }
+
+        assert_eq!(grid(0.1, 0.2, 1), vec![0.1]);
+        assert!(grid(0.1, 0.2, 0).is_empty());
+    }
+}
@@ -0,0 +1,309 @@
+//! Sliding DFT for per-sample updates of a few DFT bins.
+//!
+//! After each input sample x(n), bin k of the N-point DFT over the last N samples,
+//!
+//! > X<sub>k</sub>(n) = Σ x(n - N + 1 + m)e<sup>-jω<sub>k</sub>m</sup>,
+//! > m = 0, ..., N - 1,
+//!
+//! with ω<sub>k</sub> = 2πk/N, is updated in a constant number of operations.
+//!
+//! The textbook sliding DFT rotates each bin by the twiddle e<sup>jω<sub>k</sub></sup>
+//! at every sample, which, like `IQOsc::next()`, puts a pole on the unit circle, so
+//! roundoff error in the twiddle accumulates without bound. Two stable variants are
+//! provided:
+//!
+//! * The *modulated* sliding DFT instead accumulates the differences
+//!   (x(n) - x(n - N))e<sup>-jω<sub>k</sub>n</sup> and removes the modulation when a
+//!   bin is read. The modulation sequence for every
+//!   bin is generated by an `OscBank`, and since it's periodic with period N, the bank
+//!   is reseeded to the exact phase 0 every N samples, so its drift never builds up.
+//!   The accumulation itself has no feedback, so only the rounding of each sum
+//!   remains.
+//! * The *damped* sliding DFT scales the twiddle by r < 1, moving the pole inside the
+//!   unit circle so errors decay, at the cost of weighting the sample of age i by
+//!   r<sup>i</sup>.
+//!
+//! This module requires the `std` feature.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use bank::OscBank;
+use goertzel::Bin;
+
+/// Update rule for the bins.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Update<T: Float> {
+    /// Accumulate modulated differences.
+    Modulated {
+        /// Generates e<sup>-jω<sub>k</sub>n</sup> for each bin.
+        bank: OscBank<T>,
+    },
+    /// Rotate by damped twiddles.
+    Damped {
+        /// Holds (sin ω<sub>k</sub>, cos ω<sub>k</sub>) for each bin.
+        twiddles: Vec<(T, T)>,
+        /// Damping factor r.
+        r: T,
+        /// Holds r<sup>N</sup>.
+        rn: T,
+    },
+}
+
+/// Sliding DFT over a window of N samples, tracking a chosen set of bins.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlidingDft<T: Float> {
+    /// Holds the last N input samples as (sin, cos) pairs.
+    window: Vec<(T, T)>,
+    /// Index of the oldest sample in `window`.
+    pos: usize,
+    /// Accumulated state of each bin.
+    acc: Vec<(T, T)>,
+    /// Number of samples fed, up to N.
+    filled: usize,
+    /// Update rule.
+    update: Update<T>,
+}
+
+impl<T: Float> SlidingDft<T> {
+    /// Create a new modulated `SlidingDft` over windows of the given length N, tracking
+    /// the given bin indices k (each taken modulo N.)
+    ///
+    /// Panics if the window length is zero.
+    pub fn new(len: usize, bins: &[usize]) -> Self {
+        let steps: Vec<T> = bins.iter().map(|&k| -bin_step::<T>(k, len)).collect();
+
+        Self::with_update(len, bins.len(), Update::Modulated {
+            bank: OscBank::new(&vec![T::zero(); bins.len()], &steps),
+        })
+    }
+
+    /// Create a new damped `SlidingDft` over windows of the given length N, tracking
+    /// the given bin indices k, with the given damping factor r.
+    ///
+    /// A factor of r = 1 gives the undamped textbook sliding DFT, which drifts over
+    /// long runs. Factors slightly below 1, such as 0.9999, keep the error bounded.
+    ///
+    /// Panics if the window length is zero.
+    pub fn damped(len: usize, bins: &[usize], r: T) -> Self {
+        Self::with_update(len, bins.len(), Update::Damped {
+            twiddles: bins.iter().map(|&k| bin_step::<T>(k, len).sin_cos()).collect(),
+            r,
+            rn: r.powi(len as i32),
+        })
+    }
+
+    fn with_update(len: usize, bins: usize, update: Update<T>) -> Self {
+        assert!(len > 0, "empty window");
+
+        SlidingDft {
+            window: vec![(T::zero(), T::zero()); len],
+            pos: 0,
+            acc: vec![(T::zero(), T::zero()); bins],
+            filled: 0,
+            update,
+        }
+    }
+
+    /// Retrieve the window length N.
+    pub fn window_len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Retrieve the number of tracked bins.
+    pub fn bin_count(&self) -> usize {
+        self.acc.len()
+    }
+
+    /// Feed the given (sin, cos) sample and update every tracked bin.
+    ///
+    /// Until N samples have been fed, the window is padded with zeros.
+    pub fn feed(&mut self, sample: (T, T)) {
+        let old = self.window[self.pos];
+        self.window[self.pos] = sample;
+        self.pos = (self.pos + 1) % self.window.len();
+        self.filled = (self.filled + 1).min(self.window.len());
+
+        match self.update {
+            Update::Modulated { ref mut bank } => {
+                let d = (sample.1 - old.1, sample.0 - old.0);
+
+                let iter = self.acc.iter_mut().zip(bank.sin().iter().zip(bank.cos()));
+
+                for (acc, (&sin, &cos)) in iter {
+                    acc.0 = acc.0 + d.0 * cos - d.1 * sin;
+                    acc.1 = acc.1 + d.0 * sin + d.1 * cos;
+                }
+
+                // The modulation has period N, so restart it at the exact phase at the
+                // start of each period.
+                if self.pos == 0 {
+                    for ch in 0..bank.len() {
+                        bank.set_phase(ch, T::zero());
+                    }
+                } else {
+                    bank.advance();
+                }
+            },
+            Update::Damped { ref twiddles, r, rn } => {
+                let d = (sample.1 - rn * old.1, sample.0 - rn * old.0);
+
+                for (acc, &(sin, cos)) in self.acc.iter_mut().zip(twiddles) {
+                    let s = (r * acc.0 + d.0, r * acc.1 + d.1);
+                    *acc = (s.0 * cos - s.1 * sin, s.0 * sin + s.1 * cos);
+                }
+            },
+        }
+    }
+
+    /// Compute the current value X<sub>k</sub>(n) of the tracked bin at the given
+    /// index.
+    pub fn bin(&self, idx: usize) -> Bin<T> {
+        let acc = self.acc[idx];
+
+        let (re, im) = match self.update {
+            // The bank now holds e^(-jω(n + 1)), and X(n) = A(n)e^(jω(n + 1)).
+            Update::Modulated { ref bank } => {
+                let (sin, cos) = (bank.sin()[idx], bank.cos()[idx]);
+                (acc.0 * cos + acc.1 * sin, acc.1 * cos - acc.0 * sin)
+            },
+            Update::Damped { .. } => acc,
+        };
+
+        Bin {
+            re,
+            im,
+            samples: self.filled,
+        }
+    }
+
+    /// Compute the current value of every tracked bin.
+    pub fn bins(&self) -> Vec<Bin<T>> {
+        (0..self.acc.len()).map(|i| self.bin(i)).collect()
+    }
+}
+
+/// Compute the phase step 2πk/N of the given bin.
+fn bin_step<T: Float>(k: usize, len: usize) -> T {
+    let k = k % len;
+    T::from(2.0 * core::f64::consts::PI * k as f64 / len as f64).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use testutil::{dft, tones, Rng};
+    use IQOsc;
+
+    /// Compute every bin of the given window, weighting the sample of age i by r^i.
+    fn damped_dft(window: &[(f64, f64)], r: f64) -> Vec<(f64, f64)> {
+        let len = window.len();
+
+        let weighted: Vec<(f64, f64)> = window.iter().enumerate().map(|(m, &(s, c))| {
+            let w = r.powi((len - 1 - m) as i32);
+            (w * s, w * c)
+        }).collect();
+
+        dft(&weighted)
+    }
+
+    /// Generate a noisy two-tone test signal.
+    fn signal(samples: usize) -> Vec<(f64, f64)> {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+
+        tones(&[(1.0, 0.3, 0.0371), (0.25, -1.1, -0.213)], samples).into_iter()
+            .map(|(sin, cos)| {
+                let noise = rng.uniform() - 0.5;
+                (sin + 0.1 * noise, cos - 0.1 * noise)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_modulated() {
+        const N: usize = 64;
+        let bins = [0, 1, 2, 5, 31, 63, 64 + 7];
+        let x = signal(1_000_000);
+
+        let mut s = SlidingDft::new(N, &bins);
+        assert_eq!(s.window_len(), N);
+        assert_eq!(s.bin_count(), bins.len());
+
+        for (n, &sample) in x.iter().enumerate() {
+            s.feed(sample);
+
+            if n % 99_991 != 0 && n < x.len() - 3 {
+                continue;
+            }
+
+            let start = (n + 1).saturating_sub(N);
+            let mut window = vec![(0.0, 0.0); N - (n + 1 - start)];
+            window.extend_from_slice(&x[start..n + 1]);
+            let expected = dft(&window);
+
+            for (i, &k) in bins.iter().enumerate() {
+                let (re, im) = expected[k % N];
+                let bin = s.bin(i);
+
+                assert!((bin.re - re).abs() < 1.0e-10, "{} {} {}", n, k, bin.re - re);
+                assert!((bin.im - im).abs() < 1.0e-10, "{} {} {}", n, k, bin.im - im);
+                assert_eq!(bin.samples, (n + 1).min(N));
+            }
+        }
+    }
+
+    #[test]
+    fn test_damped() {
+        const N: usize = 50;
+        let r = 0.9999;
+        let bins = [3, 17, 49];
+        let x = signal(1_000_000);
+
+        let mut s = SlidingDft::damped(N, &bins, r);
+
+        for &sample in &x {
+            s.feed(sample);
+        }
+
+        let expected = damped_dft(&x[x.len() - N..], r);
+
+        for (bin, &k) in s.bins().iter().zip(&bins) {
+            let (re, im) = expected[k];
+            assert!((bin.re - re).abs() < 1.0e-10);
+            assert!((bin.im - im).abs() < 1.0e-10);
+        }
+    }
+
+    #[test]
+    fn test_tone() {
+        // A tone on bin 5 of a 40-point window, in single precision.
+        let step = 2.0 * PI * 5.0 / 40.0;
+        let mut osc = IQOsc::new(0.5f32, step as f32);
+        let mut s = SlidingDft::new(40, &[5, 6]);
+        let mut d = SlidingDft::damped(40, &[5, 6], 0.999);
+
+        for n in 0..200_000 {
+            let sample = osc.next();
+            s.feed(sample);
+            d.feed(sample);
+
+            if n % 40_000 == 39_999 {
+                // The phase at the window start is the tone's phase 39 samples ago.
+                let phase = 0.5 + step * (n - 39) as f64;
+                let err = (s.bin(0).phase() as f64 - phase).sin_cos();
+
+                assert!((s.bin(0).amplitude() - 1.0).abs() < 1.0e-3);
+                assert!(err.0.abs() < 1.0e-3 && err.1 > 0.0);
+                assert!(s.bin(1).amplitude() < 1.0e-3);
+
+                // Damping weights the window by Σ 0.999^i ≈ 39.23.
+                assert!((d.bin(0).magnitude() - 39.23).abs() < 0.05);
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! SigMF metadata for raw I/Q captures.
+//!
+//! A [SigMF](https://sigmf.org) recording pairs a raw `.sigmf-data` file, as written by
+//! `iqfile::IQWriter`, with a `.sigmf-meta` JSON file describing its datatype, sample
+//! rate, center frequency, and annotated segments. Only the `core` namespace is
+//! modeled here, and fields from other namespaces are ignored when parsing.
+//!
+//! This module requires the `sigmf` feature.
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use iqfile::Format;
+
+/// Version of the SigMF specification written to new metadata.
+pub const VERSION: &str = "1.0.0";
+
+/// Contents of a `.sigmf-meta` file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    /// Global information about the recording.
+    pub global: Global,
+    /// Segments of the recording with differing capture parameters.
+    #[serde(default)]
+    pub captures: Vec<Capture>,
+    /// Annotated ranges of samples.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// Global object of a SigMF recording.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Global {
+    /// Sample datatype, such as `cf32_le`.
+    #[serde(rename = "core:datatype")]
+    pub datatype: String,
+    /// Sample rate in samples per second.
+    #[serde(rename = "core:sample_rate", skip_serializing_if = "Option::is_none",
+            default)]
+    pub sample_rate: Option<f64>,
+    /// Version of the SigMF specification.
+    #[serde(rename = "core:version")]
+    pub version: String,
+    /// Description of the recording.
+    #[serde(rename = "core:description", skip_serializing_if = "Option::is_none",
+            default)]
+    pub description: Option<String>,
+    /// Author of the recording.
+    #[serde(rename = "core:author", skip_serializing_if = "Option::is_none", default)]
+    pub author: Option<String>,
+    /// Name of the program that generated the recording.
+    #[serde(rename = "core:recorder", skip_serializing_if = "Option::is_none", default)]
+    pub recorder: Option<String>,
+}
+
+/// Capture segment, starting at a sample index and lasting until the next segment.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capture {
+    /// Index of the first sample in the segment.
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    /// Center frequency of the segment in Hz.
+    #[serde(rename = "core:frequency", skip_serializing_if = "Option::is_none", default)]
+    pub frequency: Option<f64>,
+    /// ISO 8601 timestamp of the first sample.
+    #[serde(rename = "core:datetime", skip_serializing_if = "Option::is_none", default)]
+    pub datetime: Option<String>,
+}
+
+/// Annotation of a range of samples.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Index of the first annotated sample.
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    /// Number of annotated samples, or until the end of the recording if missing.
+    #[serde(rename = "core:sample_count", skip_serializing_if = "Option::is_none",
+            default)]
+    pub sample_count: Option<u64>,
+    /// Lower edge of the annotated signal in Hz.
+    #[serde(rename = "core:freq_lower_edge", skip_serializing_if = "Option::is_none",
+            default)]
+    pub freq_lower_edge: Option<f64>,
+    /// Upper edge of the annotated signal in Hz.
+    #[serde(rename = "core:freq_upper_edge", skip_serializing_if = "Option::is_none",
+            default)]
+    pub freq_upper_edge: Option<f64>,
+    /// Short label.
+    #[serde(rename = "core:label", skip_serializing_if = "Option::is_none", default)]
+    pub label: Option<String>,
+    /// Free-form comment.
+    #[serde(rename = "core:comment", skip_serializing_if = "Option::is_none", default)]
+    pub comment: Option<String>,
+}
+
+impl Meta {
+    /// Create new metadata for a recording in the given format and sample rate, with a
+    /// single capture segment at the given center frequency (in Hz.)
+    pub fn new(format: Format, sample_rate: f64, frequency: Option<f64>) -> Self {
+        Meta {
+            global: Global {
+                datatype: datatype(format).to_string(),
+                sample_rate: Some(sample_rate),
+                version: VERSION.to_string(),
+                description: None,
+                author: None,
+                recorder: Some(concat!("iq_osc ", env!("CARGO_PKG_VERSION")).to_string()),
+            },
+            captures: vec![Capture {
+                sample_start: 0,
+                frequency,
+                datetime: None,
+            }],
+            annotations: vec![],
+        }
+    }
+
+    /// Retrieve the sample format of the recording, or `None` if it isn't supported by
+    /// `iqfile`.
+    pub fn format(&self) -> Option<Format> {
+        [Format::Cf32, Format::Cf64, Format::Cs16, Format::Cs8, Format::Cu8].iter()
+            .find(|&&f| datatype(f) == self.global.datatype)
+            .cloned()
+    }
+
+    /// Retrieve the center frequency in effect at the given sample index.
+    pub fn frequency_at(&self, sample: u64) -> Option<f64> {
+        self.captures.iter()
+            .filter(|c| c.sample_start <= sample)
+            .max_by_key(|c| c.sample_start)
+            .and_then(|c| c.frequency)
+    }
+
+    /// Annotate a segment of samples containing a tone at the given baseband frequency
+    /// (in Hz), such as the output of an `IQOsc` between calls to `set_step()`.
+    ///
+    /// The annotated frequency edges are absolute, found by offsetting the tone by the
+    /// center frequency of the containing capture segment, if known.
+    pub fn annotate_tone(&mut self, sample_start: u64, sample_count: u64, freq: f64) {
+        let center = self.frequency_at(sample_start).unwrap_or(0.0);
+
+        self.annotations.push(Annotation {
+            sample_start,
+            sample_count: Some(sample_count),
+            freq_lower_edge: Some(center + freq),
+            freq_upper_edge: Some(center + freq),
+            label: Some("tone".to_string()),
+            comment: Some(format!("tone at {} Hz", freq)),
+        });
+    }
+
+    /// Serialize to SigMF JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Parse the given SigMF JSON.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Write SigMF JSON to the given writer.
+    pub fn write<W: Write>(&self, w: W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(w, self)
+    }
+
+    /// Read SigMF JSON from the given reader.
+    pub fn read<R: Read>(r: R) -> serde_json::Result<Self> {
+        serde_json::from_reader(r)
+    }
+}
+
+/// Retrieve the SigMF datatype name of the given format.
+pub fn datatype(format: Format) -> &'static str {
+    match format {
+        Format::Cf32 => "cf32_le",
+        Format::Cf64 => "cf64_le",
+        Format::Cs16 => "ci16_le",
+        Format::Cs8 => "ci8",
+        Format::Cu8 => "cu8",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut m = Meta::new(Format::Cs16, 2.4e6, Some(915.0e6));
+        m.global.description = Some("test \"tones\"".to_string());
+        m.annotate_tone(0, 1000, 10.0e3);
+        m.captures.push(Capture {
+            sample_start: 1000,
+            frequency: Some(916.0e6),
+            datetime: None,
+        });
+        m.annotate_tone(1000, 500, -25.0e3);
+
+        let json = m.to_json();
+        assert!(json.contains("\"core:datatype\": \"ci16_le\""));
+        assert!(!json.contains("core:author"));
+
+        let parsed = Meta::from_json(&json).unwrap();
+        assert_eq!(parsed, m);
+        assert_eq!(parsed.format(), Some(Format::Cs16));
+        assert_eq!(parsed.annotations[0].freq_lower_edge, Some(915.01e6));
+        assert_eq!(parsed.annotations[1].freq_upper_edge, Some(915.975e6));
+
+        let mut buf = vec![];
+        m.write(&mut buf).unwrap();
+        assert_eq!(Meta::read(&buf[..]).unwrap(), m);
+    }
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{
+            "global": {
+                "core:datatype": "cu8",
+                "core:sample_rate": 1024000,
+                "core:version": "1.0.0",
+                "core:sha512": "abc",
+                "rtlsdr:gain": 20.7
+            },
+            "captures": [
+                {"core:sample_start": 0, "core:frequency": 100e6},
+                {"core:sample_start": 2048, "core:frequency": 101e6}
+            ],
+            "annotations": [
+                {"core:sample_start": 10, "core:label": "burst", "foo:bar": [1, 2]}
+            ]
+        }"#;
+
+        let m = Meta::from_json(json).unwrap();
+        assert_eq!(m.format(), Some(Format::Cu8));
+        assert_eq!(m.global.sample_rate, Some(1024000.0));
+        assert_eq!(m.frequency_at(0), Some(100.0e6));
+        assert_eq!(m.frequency_at(2047), Some(100.0e6));
+        assert_eq!(m.frequency_at(2048), Some(101.0e6));
+        assert_eq!(m.annotations[0].label, Some("burst".to_string()));
+        assert_eq!(m.annotations[0].sample_count, None);
+
+        let m = Meta::from_json(r#"{"global": {"core:datatype": "ri16_le",
+                                               "core:version": "1.0.0"}}"#).unwrap();
+        assert_eq!(m.format(), None);
+        assert!(m.captures.is_empty());
+
+        assert!(Meta::from_json(r#"{"global": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_datatype() {
+        for &f in &[Format::Cf32, Format::Cf64, Format::Cs16, Format::Cs8, Format::Cu8] {
+            assert_eq!(Meta::new(f, 1.0, None).format(), Some(f));
+        }
+    }
+}
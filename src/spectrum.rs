@@ -0,0 +1,328 @@
+//! Spectral purity analysis of oscillator output.
+//!
+//! Samples are windowed and transformed with a built-in DFT, then the spectrum is
+//! searched for the carrier and the strongest spurious tones. This gives the
+//! spurious-free dynamic range (SFDR) and signal-to-noise ratio (SNR) of an
+//! oscillator, which pointwise error measurements don't capture.
+//!
+//! Each sample z = cos Φ + j sin Φ is treated as complex, so the spectrum is two-sided
+//! and frequencies are reported in cycles per sample in the range [-0.5, 0.5).
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use num_traits::Float;
+
+use twiddle::{self, Order};
+use window::Symmetry;
+
+pub use window::Window;
+
+/// Spurious tone found in the spectrum.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Spur {
+    /// Frequency in cycles per sample.
+    pub freq: f64,
+    /// Peak power relative to the carrier peak, in dBc.
+    pub power: f64,
+}
+
+/// Summary of the spectral purity of a captured signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PurityReport {
+    /// Number of samples analyzed.
+    pub samples: usize,
+    /// Carrier frequency in cycles per sample.
+    pub carrier_freq: f64,
+    /// Ratio of the carrier peak to the strongest spur peak, in dB.
+    pub sfdr: f64,
+    /// Ratio of the carrier power to the power in all other bins, including spurs,
+    /// in dB.
+    pub snr: f64,
+    /// Strongest spurs, in order of decreasing power.
+    pub spurs: Vec<Spur>,
+}
+
+impl fmt::Display for PurityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "samples: {}", self.samples)?;
+        writeln!(f, "carrier: {:.9} cycles/sample", self.carrier_freq)?;
+        writeln!(f, "sfdr:    {:.2} dB", self.sfdr)?;
+        write!(f, "snr:     {:.2} dB", self.snr)?;
+
+        for s in &self.spurs {
+            write!(f, "\nspur:    {:.9} cycles/sample at {:.2} dBc", s.freq, s.power)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Capture the given number of samples from an oscillator and analyze them.
+///
+/// The `next` closure returns (sin, cos) at each step, for example `|| osc.next()` for
+/// an `IQOsc`. Up to `spurs` of the strongest spurs are listed in the report.
+///
+/// Panics if the number of samples is zero.
+pub fn analyze<T, F>(samples: usize, window: Window, spurs: usize, mut next: F)
+    -> PurityReport
+    where T: Float, F: FnMut() -> (T, T)
+{
+    let buf: Vec<(T, T)> = (0..samples).map(|_| next()).collect();
+    analyze_samples(&buf, window, spurs)
+}
+
+/// Analyze the given (sin, cos) samples.
+///
+/// Power-of-two lengths use a radix-2 FFT, and other lengths fall back to a direct
+/// DFT, which is much slower for large captures.
+///
+/// Panics if no samples are given.
+pub fn analyze_samples<T: Float>(samples: &[(T, T)], window: Window, spurs: usize)
+    -> PurityReport
+{
+    let len = samples.len();
+    assert!(len > 0, "no samples to analyze");
+
+    let coefs: Vec<f64> = window.generate(len, Symmetry::Periodic);
+    let mut buf: Vec<(f64, f64)> = samples.iter().zip(coefs).map(|(&(sin, cos), w)| {
+        (w * cos.to_f64().unwrap(), w * sin.to_f64().unwrap())
+    }).collect();
+
+    dft(&mut buf);
+
+    let power: Vec<f64> = buf.iter().map(|&(re, im)| re * re + im * im).collect();
+    let half = half_width(window);
+
+    let carrier = (0..len).fold(0, |max, k| if power[k] > power[max] { k } else { max });
+    let in_lobe = |k: usize, center: usize| circ_dist(k, center, len) <= half;
+
+    let signal: f64 = (0..len).filter(|&k| in_lobe(k, carrier)).map(|k| power[k]).sum();
+    let total: f64 = power.iter().sum();
+
+    // Find local maxima outside the carrier lobe, strongest first.
+    let mut peaks: Vec<usize> = (0..len).filter(|&k| {
+        !in_lobe(k, carrier) &&
+            power[k] > power[(k + len - 1) % len] &&
+            power[k] >= power[(k + 1) % len]
+    }).collect();
+    peaks.sort_by(|&a, &b| power[b].total_cmp(&power[a]));
+
+    // Skip peaks that are only sidelobes of stronger spurs.
+    let mut found: Vec<usize> = vec![];
+
+    for k in peaks {
+        if found.len() == spurs {
+            break;
+        }
+
+        if found.iter().all(|&s| !in_lobe(k, s)) {
+            found.push(k);
+        }
+    }
+
+    let sfdr = match found.first() {
+        Some(&k) => db(power[carrier] / power[k]),
+        None => f64::INFINITY,
+    };
+
+    PurityReport {
+        samples: len,
+        carrier_freq: peak_freq(&power, carrier),
+        sfdr,
+        snr: db(signal / (total - signal)),
+        spurs: found.into_iter().map(|k| Spur {
+            freq: peak_freq(&power, k),
+            power: db(power[k] / power[carrier]),
+        }).collect(),
+    }
+}
+
+/// Number of bins on each side of a peak covered by the given window's main lobe.
+fn half_width(window: Window) -> usize {
+    match window {
+        Window::Rectangular => 1,
+        Window::Hann | Window::Hamming => 2,
+        Window::Blackman => 3,
+        Window::BlackmanHarris | Window::Nuttall => 4,
+        Window::FlatTop => 5,
+    }
+}
+
+/// Convert the given power ratio to decibels.
+fn db(ratio: f64) -> f64 {
+    10.0 * ratio.log10()
+}
+
+/// Compute the distance between two bins, wrapping around the spectrum.
+fn circ_dist(a: usize, b: usize, len: usize) -> usize {
+    let d = a.abs_diff(b);
+    d.min(len - d)
+}
+
+/// Estimate the frequency of the peak at the given bin by fitting a parabola to the
+/// magnitude of it and its neighbors.
+fn peak_freq(power: &[f64], k: usize) -> f64 {
+    let len = power.len();
+    let (a, b, c) = (
+        power[(k + len - 1) % len].sqrt(),
+        power[k].sqrt(),
+        power[(k + 1) % len].sqrt(),
+    );
+
+    let denom = a - 2.0 * b + c;
+    let offset = if denom.is_finite() && denom != 0.0 {
+        (0.5 * (a - c) / denom).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+
+    let freq = (k as f64 + offset) / len as f64;
+
+    if freq >= 0.5 { freq - 1.0 } else { freq }
+}
+
+/// Compute the DFT of the given (re, im) samples in place.
+fn dft(buf: &mut [(f64, f64)]) {
+    if buf.len().is_power_of_two() {
+        fft(buf);
+    } else {
+        direct_dft(buf);
+    }
+}
+
+/// Compute the DFT of the given (re, im) samples in place by direct summation.
+pub(crate) fn direct_dft(buf: &mut [(f64, f64)]) {
+    let len = buf.len();
+    let input = buf.to_vec();
+
+    for (k, out) in buf.iter_mut().enumerate() {
+        *out = input.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &(xr, xi))| {
+            let (sin, cos) = (-2.0 * PI * ((k * n) % len) as f64 / len as f64).sin_cos();
+            (re + xr * cos - xi * sin, im + xr * sin + xi * cos)
+        });
+    }
+}
+
+/// Compute the DFT of the given power-of-two length (re, im) samples in place using
+/// an iterative radix-2 decimation-in-time FFT.
+pub(crate) fn fft(buf: &mut [(f64, f64)]) {
+    let len = buf.len();
+    let bits = len.trailing_zeros();
+
+    if bits == 0 {
+        return;
+    }
+
+    for i in 0..len {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+
+    // Each stage uses every (N / size)th twiddle of the full table.
+    let table = twiddle::table::<f64>(len, Order::Natural);
+    let mut size = 2;
+
+    while size <= len {
+        let twiddles = table.iter().step_by(len / size).take(size / 2);
+
+        for start in (0..len).step_by(size) {
+            for (k, &(wi, wr)) in twiddles.clone().enumerate() {
+                let (ar, ai) = buf[start + k];
+                let (br, bi) = buf[start + k + size / 2];
+                let (tr, ti) = (br * wr - bi * wi, br * wi + bi * wr);
+
+                buf[start + k] = (ar + tr, ai + ti);
+                buf[start + k + size / 2] = (ar - tr, ai - ti);
+            }
+        }
+
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use IQOsc;
+
+    #[test]
+    fn test_dft() {
+        // Compare the FFT against the direct DFT.
+        let input: Vec<(f64, f64)> = (0..64).map(|n| {
+            let n = n as f64;
+            ((0.3 * n).cos() + 0.1 * n, (1.7 * n).sin())
+        }).collect();
+
+        let mut fast = input.clone();
+        fft(&mut fast);
+
+        let mut direct = input.clone();
+        direct_dft(&mut direct);
+
+        for (a, b) in fast.iter().zip(direct.iter()) {
+            assert!((a.0 - b.0).abs() < 1.0e-9);
+            assert!((a.1 - b.1).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_pure() {
+        let mut o = IQOsc::new(0.0, 2.0 * PI * 100.0 / 4096.0);
+        let r = analyze(4096, Window::Rectangular, 4, || o.next());
+
+        assert!((r.carrier_freq - 100.0 / 4096.0).abs() < 1.0e-9);
+        assert!(r.sfdr > 200.0);
+        assert!(r.snr > 200.0);
+    }
+
+    #[test]
+    fn test_spur() {
+        // Add a spur 60 dB below the carrier.
+        let mut n = 0.0;
+        let r = analyze(4096, Window::BlackmanHarris, 3, || {
+            let (sin, cos) = (0.1 * 2.0 * PI * n).sin_cos();
+            let (ssin, scos) = (-0.2371 * 2.0 * PI * n).sin_cos();
+            n += 1.0;
+            (sin + 1.0e-3 * ssin, cos + 1.0e-3 * scos)
+        });
+
+        assert!((r.carrier_freq - 0.1).abs() < 1.0e-4);
+        assert!((r.sfdr - 60.0).abs() < 1.0);
+        assert!((r.snr - 60.0).abs() < 1.0);
+        assert!((r.spurs[0].freq - -0.2371).abs() < 1.0e-4);
+        assert!((r.spurs[0].power - -60.0).abs() < 1.0);
+        assert!(r.spurs[1].power < -90.0);
+    }
+
+    #[test]
+    fn test_non_finite() {
+        // Non-finite samples give a meaningless report rather than a panic.
+        for &bad in &[f64::NAN, f64::INFINITY] {
+            let mut n = 0;
+
+            analyze(256, Window::Hann, 4, || {
+                n += 1;
+                if n % 50 == 0 { (bad, 1.0) } else { (0.0, 1.0) }
+            });
+        }
+    }
+
+    #[test]
+    fn test_non_pow2() {
+        let mut o = IQOsc::new(0.0f32, (2.0 * PI * 7.0 / 90.0) as f32);
+        let r = analyze(90, Window::Hann, 2, || o.next());
+
+        assert!((r.carrier_freq - 7.0 / 90.0).abs() < 1.0e-6);
+        assert!(r.sfdr > 100.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        analyze_samples::<f64>(&[], Window::Hann, 2);
+    }
+}
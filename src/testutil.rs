@@ -0,0 +1,66 @@
+//! Fixtures shared by the unit tests.
+
+use std::f64::consts::PI;
+
+#[cfg(feature = "std")]
+use spectrum::direct_dft;
+#[cfg(feature = "std")]
+use IQOsc;
+
+/// Generate uniform random numbers and complex Gaussian noise with a xorshift
+/// generator, seeded with the given nonzero state.
+pub struct Rng(pub u64);
+
+impl Rng {
+    /// Generate the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Generate a uniform number in (0, 1).
+    pub fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// Generate a (sin, cos) sample of complex Gaussian noise with the given standard
+    /// deviation per component.
+    pub fn noise(&mut self, sigma: f64) -> (f64, f64) {
+        let r = sigma * (-2.0 * self.uniform().ln()).sqrt();
+        let (sin, cos) = (2.0 * PI * self.uniform()).sin_cos();
+        (r * sin, r * cos)
+    }
+}
+
+/// Generate the sum of the given tones, each given as (amplitude, initial phase,
+/// frequency in cycles per sample), as (sin, cos) samples.
+#[cfg(feature = "std")]
+pub fn tones(tones: &[(f64, f64, f64)], len: usize) -> Vec<(f64, f64)> {
+    let mut oscs: Vec<(f64, IQOsc<f64>)> = tones.iter()
+        .map(|&(amp, phase, freq)| (amp, IQOsc::new(phase, 2.0 * PI * freq)))
+        .collect();
+
+    (0..len).map(|_| {
+        oscs.iter_mut().fold((0.0, 0.0), |acc, &mut (amp, ref mut osc)| {
+            let (sin, cos) = osc.next();
+            (acc.0 + amp * sin, acc.1 + amp * cos)
+        })
+    }).collect()
+}
+
+/// Generate a unit tone at the given frequency (in cycles per sample.)
+#[cfg(feature = "std")]
+pub fn tone(freq: f64, len: usize) -> Vec<(f64, f64)> {
+    tones(&[(1.0, 0.2, freq)], len)
+}
+
+/// Compute the DFT of the given (sin, cos) samples by direct summation, returning
+/// each bin as (re, im).
+#[cfg(feature = "std")]
+pub fn dft(x: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut buf: Vec<(f64, f64)> = x.iter().map(|&(sin, cos)| (cos, sin)).collect();
+    direct_dft(&mut buf);
+    buf
+}
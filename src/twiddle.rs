@@ -0,0 +1,230 @@
+//! FFT twiddle-factor tables without a trig call per entry.
+//!
+//! A table of length N holds the twiddle factors W<sup>k</sup> = e<sup>-j2πk/N</sup>
+//! for k = 0, ..., N - 1, each as a (sin, cos) pair to match `IQOsc::next()`. Tables
+//! for an inverse FFT are found by negating each sin component.
+//!
+//! Entries are generated by an `IQOsc<DoubleDouble>` rather than by calling `sin_cos`
+//! for each entry. To keep the error of the recurrence bounded for any N, the table is
+//! split into segments, and the oscillator is reseeded with an exact double-double
+//! phase at the start of each segment. When N is a multiple of 8, only the first octant
+//! is generated, and the rest of the table is filled in by exact symmetries, so
+//! entries such as W<sup>N/4</sup> = -j are exact. With the recurrence carried out in
+//! double-double precision, each entry is within about half a ULP of the exact value
+//! in both `f32` and `f64`.
+//!
+//! This module requires the `std` feature.
+
+use num_traits::Float;
+
+use dd::DoubleDouble;
+use IQOsc;
+
+/// Number of entries generated by the recurrence between exact reseeds.
+const SEGMENT: usize = 1024;
+
+/// Order of the entries in a table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Entry k holds W<sup>k</sup>.
+    Natural,
+    /// Entry k holds W<sup>r(k)</sup>, where r(k) reverses the bits of k, as used by
+    /// in-place FFTs that skip the input or output permutation. This requires a
+    /// power-of-two length.
+    BitReversed,
+}
+
+/// Build a table of the N twiddle factors for the given length N in the given order.
+///
+/// Panics if bit-reversed order is requested for a length that isn't a power of two.
+pub fn table<T: Float>(len: usize, order: Order) -> Vec<(T, T)> {
+    let conv = |(sin, cos): (f64, f64)| (T::from(sin).unwrap(), T::from(cos).unwrap());
+
+    if len == 0 {
+        return vec![];
+    }
+
+    if len % 8 != 0 {
+        let entries = generate(len, len).into_iter()
+            .map(|(sin, cos)| conv((-sin, cos)))
+            .collect();
+
+        return reorder(entries, order);
+    }
+
+    let oct = len / 8;
+    let base = generate(len, oct + 1);
+
+    let entries = (0..len).map(|k| {
+        let quad = k / (2 * oct);
+        let r = k % (2 * oct);
+
+        // Compute (sin θ, cos θ) for θ = 2πr/N in the first quadrant, using
+        // sin(π/2 - x) = cos x for the second octant.
+        let (sin, cos) = if r <= oct {
+            base[r]
+        } else {
+            let (sin, cos) = base[2 * oct - r];
+            (cos, sin)
+        };
+
+        // Rotate into the quadrant by multiples of π/2.
+        let (sin, cos) = match quad {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        };
+
+        // Conjugate for e^(-jθ).
+        conv((-sin, cos))
+    }).collect();
+
+    reorder(entries, order)
+}
+
+/// Compute (sin θ, cos θ) for θ = 2πk/N for the first given number of k.
+fn generate(len: usize, count: usize) -> Vec<(f64, f64)> {
+    let step = DoubleDouble::TAU / DoubleDouble::from(len as f64);
+    let mut out = Vec::with_capacity(count);
+
+    for start in (0..count).step_by(SEGMENT) {
+        let mut osc = IQOsc::new(step * DoubleDouble::from(start as f64), step);
+
+        for _ in start..count.min(start + SEGMENT) {
+            let (sin, cos) = osc.next();
+            out.push((sin.to_f64(), cos.to_f64()));
+        }
+    }
+
+    out
+}
+
+/// Permute the given natural-order table into the given order.
+fn reorder<T: Copy>(mut entries: Vec<T>, order: Order) -> Vec<T> {
+    if order == Order::Natural {
+        return entries;
+    }
+
+    let len = entries.len();
+    assert!(len.is_power_of_two(), "bit-reversed order requires a power of two");
+
+    let bits = len.trailing_zeros();
+
+    if bits == 0 {
+        return entries;
+    }
+
+    for i in 0..len {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+
+        if i < j {
+            entries.swap(i, j);
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// Find the maximum error of the given table compared to the given reference.
+    fn max_err<T, F>(table: &[(T, T)], reference: F) -> f64
+        where T: Float, F: Fn(usize, usize) -> (f64, f64)
+    {
+        let len = table.len();
+
+        table.iter().enumerate().fold(0.0, |max, (k, &(sin, cos))| {
+            let (esin, ecos) = reference(k, len);
+            max.max((sin.to_f64().unwrap() - esin).abs())
+                .max((cos.to_f64().unwrap() - ecos).abs())
+        })
+    }
+
+    /// Compute W^k in double-double precision.
+    fn dd_ref(k: usize, len: usize) -> (f64, f64) {
+        let x = DoubleDouble::TAU * DoubleDouble::from(k as f64) /
+            DoubleDouble::from(len as f64);
+        let (sin, cos) = x.sin_cos();
+
+        (-sin.to_f64(), cos.to_f64())
+    }
+
+    /// Compute W^k with direct trig, first reducing the angle exactly to within π/4 of
+    /// a multiple of π/2 so the rounding of the angle doesn't dominate.
+    fn trig_ref(k: usize, len: usize) -> (f64, f64) {
+        let quad = (4 * k + len / 2) / len;
+        let r = k as f64 - (quad * len / 4) as f64;
+        let (sin, cos) = (2.0 * PI * r / len as f64).sin_cos();
+
+        let (sin, cos) = match quad % 4 {
+            0 => (sin, cos),
+            1 => (cos, -sin),
+            2 => (-sin, -cos),
+            _ => (-cos, sin),
+        };
+
+        (-sin, cos)
+    }
+
+    #[test]
+    fn test_small() {
+        assert!(table::<f64>(0, Order::Natural).is_empty());
+        assert_eq!(table::<f64>(1, Order::BitReversed), vec![(0.0, 1.0)]);
+        assert_eq!(table::<f64>(2, Order::Natural), vec![(0.0, 1.0), (0.0, -1.0)]);
+
+        for &len in &[3, 5, 6, 7, 12, 100, 1000, 3000] {
+            let err = max_err(&table::<f64>(len, Order::Natural), dd_ref);
+            assert!(err <= 1.2e-16, "{} {}", len, err);
+        }
+    }
+
+    #[test]
+    fn test_exact() {
+        let t = table::<f64>(64, Order::Natural);
+        assert_eq!(t[0], (0.0, 1.0));
+        assert_eq!(t[16], (-1.0, 0.0));
+        assert_eq!(t[32], (0.0, -1.0));
+        assert_eq!(t[48], (1.0, 0.0));
+        assert_eq!(t[8].0, -t[8].1);
+
+        // Octant symmetry leaves every entry on the unit circle to within rounding.
+        for &(sin, cos) in &t {
+            assert!((sin * sin + cos * cos - 1.0).abs() < 4.5e-16);
+        }
+    }
+
+    #[test]
+    fn test_bit_reversed() {
+        let nat = table::<f32>(256, Order::Natural);
+        let rev = table::<f32>(256, Order::BitReversed);
+
+        for (k, &r) in rev.iter().enumerate() {
+            assert_eq!(r, nat[(k as u8).reverse_bits() as usize]);
+        }
+
+        assert_eq!(rev[1], (0.0, -1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bit_reversed_len() {
+        table::<f64>(24, Order::BitReversed);
+    }
+
+    #[test]
+    fn test_large() {
+        for bits in (4..25).step_by(4) {
+            let len = 1 << bits;
+
+            // Allow for the rounding of both the table and the reference.
+            let err = max_err(&table::<f64>(len, Order::Natural), trig_ref);
+            assert!(err <= 2.3e-16, "{} {}", bits, err);
+            let err = max_err(&table::<f32>(len, Order::Natural), trig_ref);
+            assert!(err <= 6.0e-8, "{} {}", bits, err);
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! WAV export and import of oscillator output.
+//!
+//! Mono files hold only the cos (I) component, which is a plain test tone, and stereo
+//! files hold I in the left channel and Q in the right, as expected by SDR software
+//! that accepts baseband recordings as audio. Samples are 16- or 24-bit PCM or 32-bit
+//! float, with full scale at ±1.0.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use num_traits::Float;
+
+/// Size field written while the length of the data is unknown.
+const UNKNOWN_SIZE: u32 = 0xffff_ffff;
+
+/// Encoding of each sample.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Signed 16-bit PCM.
+    Pcm16,
+    /// Signed 24-bit PCM.
+    Pcm24,
+    /// IEEE 32-bit float.
+    Float32,
+}
+
+impl SampleFormat {
+    /// Number of bytes in each sample.
+    fn size(&self) -> usize {
+        match *self {
+            SampleFormat::Pcm16 => 2,
+            SampleFormat::Pcm24 => 3,
+            SampleFormat::Float32 => 4,
+        }
+    }
+
+    /// Encode the given sample into the given buffer.
+    fn encode(&self, x: f64, buf: &mut Vec<u8>) {
+        let x = x.clamp(-1.0, 1.0);
+
+        match *self {
+            SampleFormat::Pcm16 =>
+                buf.extend_from_slice(&((x * 32767.0).round() as i16).to_le_bytes()),
+            SampleFormat::Pcm24 => {
+                let x = (x * 8388607.0).round() as i32;
+                buf.extend_from_slice(&x.to_le_bytes()[..3])
+            },
+            SampleFormat::Float32 => buf.extend_from_slice(&(x as f32).to_le_bytes()),
+        }
+    }
+
+    /// Decode the sample at the start of the given buffer.
+    fn decode(&self, b: &[u8]) -> f64 {
+        match *self {
+            SampleFormat::Pcm16 => i16::from_le_bytes([b[0], b[1]]) as f64 / 32767.0,
+            // Place the 24 bits at the top of an i32 to sign-extend them.
+            SampleFormat::Pcm24 =>
+                (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f64 / 8388607.0,
+            SampleFormat::Float32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        }
+    }
+}
+
+/// Layout of a WAV file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WavSpec {
+    /// Whether to store both I and Q (stereo) or only I (mono.)
+    pub stereo: bool,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Encoding of each sample.
+    pub format: SampleFormat,
+}
+
+impl WavSpec {
+    /// Number of channels.
+    fn channels(&self) -> u16 {
+        if self.stereo { 2 } else { 1 }
+    }
+
+    /// Number of bytes in each frame of all channels.
+    fn frame_size(&self) -> usize {
+        self.channels() as usize * self.format.size()
+    }
+
+    /// Number of bytes per second, if it fits in the 32-bit header field.
+    fn byte_rate(&self) -> Option<u32> {
+        self.sample_rate.checked_mul(self.frame_size() as u32)
+    }
+
+    /// Check that the layout can be written to a WAV header.
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` if the byte rate, the sample rate times
+    /// the frame size, doesn't fit in the 32-bit header field.
+    pub fn validate(&self) -> io::Result<()> {
+        match self.byte_rate() {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                       "sample rate too high for WAV")),
+        }
+    }
+}
+
+/// Writes oscillator samples to a WAV file.
+///
+/// The header is written with unknown sizes, as for a stream, and `finish()` fills in
+/// the actual sizes when the underlying writer is seekable.
+pub struct WavWriter<W: Write> {
+    /// Underlying writer.
+    inner: W,
+    /// File layout.
+    spec: WavSpec,
+    /// Number of frames written so far.
+    frames: u64,
+    /// Holds encoded bytes before they're written.
+    buf: Vec<u8>,
+}
+
+impl<W: Write> WavWriter<W> {
+    /// Create a new `WavWriter` with the given layout and write the header.
+    ///
+    /// Fails as `WavSpec::validate()` if the layout can't be written.
+    pub fn new(mut inner: W, spec: WavSpec) -> io::Result<Self> {
+        spec.validate()?;
+        let byte_rate = spec.byte_rate().unwrap();
+        let channels = spec.channels();
+        let bits = 8 * spec.format.size() as u16;
+        let tag: u16 = if spec.format == SampleFormat::Float32 { 3 } else { 1 };
+
+        let mut hdr = Vec::with_capacity(44);
+        hdr.extend_from_slice(b"RIFF");
+        hdr.extend_from_slice(&UNKNOWN_SIZE.to_le_bytes());
+        hdr.extend_from_slice(b"WAVEfmt ");
+        hdr.extend_from_slice(&16u32.to_le_bytes());
+        hdr.extend_from_slice(&tag.to_le_bytes());
+        hdr.extend_from_slice(&channels.to_le_bytes());
+        hdr.extend_from_slice(&spec.sample_rate.to_le_bytes());
+        hdr.extend_from_slice(&byte_rate.to_le_bytes());
+        hdr.extend_from_slice(&(spec.frame_size() as u16).to_le_bytes());
+        hdr.extend_from_slice(&bits.to_le_bytes());
+        hdr.extend_from_slice(b"data");
+        hdr.extend_from_slice(&UNKNOWN_SIZE.to_le_bytes());
+
+        inner.write_all(&hdr)?;
+
+        Ok(WavWriter {
+            inner,
+            spec,
+            frames: 0,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Write the given (sin, cos) samples as frames.
+    pub fn write_samples<T: Float>(&mut self, samples: &[(T, T)]) -> io::Result<()> {
+        self.buf.clear();
+
+        for &(sin, cos) in samples {
+            self.spec.format.encode(cos.to_f64().unwrap(), &mut self.buf);
+
+            if self.spec.stereo {
+                self.spec.format.encode(sin.to_f64().unwrap(), &mut self.buf);
+            }
+        }
+
+        self.frames += samples.len() as u64;
+        self.inner.write_all(&self.buf)
+    }
+
+    /// Write the given number of frames from an oscillator, where the `next` closure
+    /// returns (sin, cos) at each step, for example `|| osc.next()` for an `IQOsc`.
+    pub fn write_osc<T, F>(&mut self, frames: u64, mut next: F) -> io::Result<()>
+        where T: Float, F: FnMut() -> (T, T)
+    {
+        const BLOCK: u64 = 4096;
+
+        let mut block = Vec::with_capacity(BLOCK.min(frames) as usize);
+        let mut left = frames;
+
+        while left > 0 {
+            let n = left.min(BLOCK);
+
+            block.clear();
+            block.extend((0..n).map(|_| next()));
+            self.write_samples(&block)?;
+
+            left -= n;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve the number of frames written so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Retrieve the underlying writer, leaving the sizes in the header unknown.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Fill in the sizes in the header and retrieve the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let data = self.frames * self.spec.frame_size() as u64;
+
+        if data + 36 > UNKNOWN_SIZE as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "data too large for WAV"));
+        }
+
+        self.inner.seek(SeekFrom::Start(4))?;
+        self.inner.write_all(&(data as u32 + 36).to_le_bytes())?;
+        self.inner.seek(SeekFrom::Start(40))?;
+        self.inner.write_all(&(data as u32).to_le_bytes())?;
+        self.inner.seek(SeekFrom::End(0))?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+/// Reads samples from a WAV file.
+pub struct WavReader<R: Read> {
+    /// Underlying reader.
+    inner: R,
+    /// File layout.
+    spec: WavSpec,
+    /// Number of data bytes left, or `None` if unknown.
+    left: Option<u64>,
+    /// Holds the raw bytes of each frame.
+    buf: Vec<u8>,
+}
+
+impl<R: Read> WavReader<R> {
+    /// Create a new `WavReader` and parse the header, up to the start of the data.
+    ///
+    /// Mono and stereo files with 16- or 24-bit PCM or 32-bit float samples are
+    /// supported, and unrecognized chunks are skipped.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut riff = [0; 12];
+        inner.read_exact(&mut riff)?;
+
+        if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+            return Err(invalid("not a WAV file"));
+        }
+
+        let mut spec = None;
+
+        loop {
+            let mut chunk = [0; 8];
+            inner.read_exact(&mut chunk)?;
+
+            let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+
+            match &chunk[..4] {
+                b"fmt " => {
+                    let mut fmt = vec![0; size as usize + (size & 1) as usize];
+                    inner.read_exact(&mut fmt)?;
+                    spec = Some(parse_fmt(&fmt)?);
+                },
+                b"data" => {
+                    let spec = spec.ok_or_else(|| invalid("data before fmt chunk"))?;
+
+                    return Ok(WavReader {
+                        inner,
+                        spec,
+                        left: if size == UNKNOWN_SIZE { None } else { Some(size as u64) },
+                        buf: vec![0; spec.frame_size()],
+                    });
+                },
+                _ => {
+                    // Chunks are padded to an even size.
+                    let skip = size as u64 + (size & 1) as u64;
+                    io::copy(&mut (&mut inner).take(skip), &mut io::sink())?;
+                },
+            }
+        }
+    }
+
+    /// Retrieve the layout of the file.
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Read the next frame as a (sin, cos) sample, or `None` at the end of the data.
+    ///
+    /// The sin component of mono frames is always zero.
+    pub fn read_frame<T: Float>(&mut self) -> io::Result<Option<(T, T)>> {
+        let size = self.buf.len() as u64;
+
+        match self.left {
+            Some(left) if left < size => return Ok(None),
+            _ => {},
+        }
+
+        let mut filled = 0;
+
+        while filled < self.buf.len() {
+            match self.inner.read(&mut self.buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                   "partial WAV frame")),
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        if let Some(ref mut left) = self.left {
+            *left -= size;
+        }
+
+        let fmt = self.spec.format;
+        let cos = fmt.decode(&self.buf);
+        let sin = if self.spec.stereo {
+            fmt.decode(&self.buf[fmt.size()..])
+        } else {
+            0.0
+        };
+
+        Ok(Some((T::from(sin).unwrap(), T::from(cos).unwrap())))
+    }
+}
+
+/// Render the given duration (in seconds) of an oscillator to a new WAV file at the
+/// given path, where the `next` closure returns (sin, cos) at each step.
+///
+/// The oscillator's phase step should be based on the sample rate in `spec`, for
+/// example 2πf / `spec.sample_rate` for a tone at f Hz.
+pub fn render<P, T, F>(path: P, spec: WavSpec, duration: f64, next: F) -> io::Result<()>
+    where P: AsRef<Path>, T: Float, F: FnMut() -> (T, T)
+{
+    let frames = (duration * spec.sample_rate as f64).round() as u64;
+
+    let mut w = WavWriter::new(BufWriter::new(File::create(path)?), spec)?;
+    w.write_osc(frames, next)?;
+    w.finish()?;
+
+    Ok(())
+}
+
+/// Parse the contents of a fmt chunk.
+fn parse_fmt(fmt: &[u8]) -> io::Result<WavSpec> {
+    if fmt.len() < 16 {
+        return Err(invalid("short fmt chunk"));
+    }
+
+    let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+
+    let mut tag = u16_at(0);
+    let channels = u16_at(2);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits = u16_at(14);
+
+    // WAVE_FORMAT_EXTENSIBLE stores the actual format tag in its subformat GUID.
+    if tag == 0xfffe && fmt.len() >= 26 {
+        tag = u16_at(24);
+    }
+
+    let format = match (tag, bits) {
+        (1, 16) => SampleFormat::Pcm16,
+        (1, 24) => SampleFormat::Pcm24,
+        (3, 32) => SampleFormat::Float32,
+        _ => return Err(invalid("unsupported WAV sample format")),
+    };
+
+    let stereo = match channels {
+        1 => false,
+        2 => true,
+        _ => return Err(invalid("unsupported number of WAV channels")),
+    };
+
+    Ok(WavSpec { stereo, sample_rate, format })
+}
+
+/// Create an error for invalid file contents.
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::io::Cursor;
+    use IQOsc;
+
+    fn round_trip(stereo: bool, format: SampleFormat, tol: f64) {
+        let spec = WavSpec { stereo, sample_rate: 48000, format };
+
+        let mut w = WavWriter::new(Cursor::new(vec![]), spec).unwrap();
+        let mut o = IQOsc::new(0.0, 0.1);
+        w.write_osc(1000, || o.next()).unwrap();
+        let bytes = w.finish().unwrap().into_inner();
+
+        let data = 1000 * spec.frame_size() as u32;
+        assert_eq!(bytes.len(), 44 + data as usize);
+        assert_eq!(&bytes[4..8], &(data + 36).to_le_bytes());
+        assert_eq!(&bytes[40..44], &data.to_le_bytes());
+
+        let mut r = WavReader::new(&bytes[..]).unwrap();
+        assert_eq!(r.spec(), spec);
+
+        let mut o = IQOsc::new(0.0, 0.1);
+        let mut n = 0;
+
+        while let Some((sin, cos)) = r.read_frame::<f64>().unwrap() {
+            let (esin, ecos) = o.next();
+            assert!((cos - ecos).abs() <= tol);
+            assert!((sin - if stereo { esin } else { 0.0 }).abs() <= tol);
+            n += 1;
+        }
+
+        assert_eq!(n, 1000);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &stereo in &[false, true] {
+            round_trip(stereo, SampleFormat::Pcm16, 0.5 / 32767.0);
+            round_trip(stereo, SampleFormat::Pcm24, 0.5 / 8388607.0);
+            round_trip(stereo, SampleFormat::Float32, 1.0e-7);
+        }
+    }
+
+    #[test]
+    fn test_stream() {
+        // Unknown sizes should be read until the end of the stream, and unknown chunks
+        // skipped.
+        let spec = WavSpec {
+            stereo: true,
+            sample_rate: 8000,
+            format: SampleFormat::Pcm16,
+        };
+
+        let mut w = WavWriter::new(vec![], spec).unwrap();
+        w.write_samples(&[(0.5, -1.0), (-2.0, 0.25)]).unwrap();
+        assert_eq!(w.frames(), 2);
+
+        let mut bytes = w.into_inner();
+        assert_eq!(&bytes[40..44], &[0xff; 4]);
+
+        let list = b"LIST\x03\x00\x00\x00abc\x00";
+        let data = bytes.split_off(36);
+        bytes.extend_from_slice(list);
+        bytes.extend_from_slice(&data);
+
+        let mut r = WavReader::new(&bytes[..]).unwrap();
+        assert_eq!(r.read_frame().unwrap(), Some((16384.0 / 32767.0, -1.0)));
+        assert_eq!(r.read_frame().unwrap(), Some((-1.0, 8192.0 / 32767.0)));
+        assert_eq!(r.read_frame::<f64>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(WavReader::new(&b"RIFF\x00\x00\x00\x00AVI "[..]).is_err());
+
+        let mut bytes = WavWriter::new(vec![], WavSpec {
+            stereo: false,
+            sample_rate: 8000,
+            format: SampleFormat::Pcm16,
+        }).unwrap().into_inner();
+
+        // Change to 8-bit samples.
+        bytes[34] = 8;
+        assert!(WavReader::new(&bytes[..]).is_err());
+
+        // The byte rate of 1 GHz stereo floats overflows the header.
+        let spec = WavSpec {
+            stereo: true,
+            sample_rate: 1_000_000_000,
+            format: SampleFormat::Float32,
+        };
+        assert_eq!(spec.validate().err().unwrap().kind(), io::ErrorKind::InvalidInput);
+        let err = WavWriter::new(vec![], spec).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_render() {
+        let path = env::temp_dir().join("iq_osc_test_render.wav");
+        let spec = WavSpec {
+            stereo: false,
+            sample_rate: 8000,
+            format: SampleFormat::Pcm24,
+        };
+
+        let step = 2.0 * ::std::f64::consts::PI * 1000.0 / 8000.0;
+        let mut o = IQOsc::new(0.0, step);
+        render(&path, spec, 0.5, || o.next()).unwrap();
+
+        let mut r = WavReader::new(File::open(&path).unwrap()).unwrap();
+        let mut n = 0;
+
+        while let Some((_, cos)) = r.read_frame::<f64>().unwrap() {
+            assert!((cos - (step * n as f64).cos()).abs() < 1.0e-6);
+            n += 1;
+        }
+
+        assert_eq!(n, 4000);
+
+        ::std::fs::remove_file(&path).unwrap();
+    }
+}
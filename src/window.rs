@@ -0,0 +1,207 @@
+//! Cosine-sum window functions generated without trig calls.
+//!
+//! Each window here is a sum of cosines,
+//!
+//! > w(n) = Σ (-1)<sup>k</sup>a<sub>k</sub> cos(2πkn/D), k = 0, ..., K,
+//!
+//! where D = N - 1 for a symmetric window of length N, as used for filter design, and
+//! D = N for a periodic window, as used for spectral analysis. Rather than calling
+//! `cos` for every term of every sample, the harmonics cos(2πkn/D) are generated by an
+//! `OscBank` with a channel for each k. To keep the error of the recurrence bounded
+//! for long windows, the bank is reseeded with exact phases at the start of each
+//! segment of samples. Only the first half of the window is generated, and the second
+//! half is mirrored from it, so the result is exactly symmetric.
+//!
+//! This module requires the `std` feature.
+
+use core::f64::consts::PI;
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use bank::OscBank;
+
+/// Number of samples generated by the recurrence between exact reseeds.
+const SEGMENT: usize = 1024;
+
+/// Window function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Window {
+    /// No windowing, best when the carrier lies exactly on a DFT bin.
+    Rectangular,
+    /// Hann window, with sidelobes below -31 dB.
+    Hann,
+    /// Hamming window, with sidelobes below -42 dB.
+    Hamming,
+    /// Classic Blackman window, with sidelobes below -58 dB.
+    Blackman,
+    /// 4-term Blackman-Harris window, with sidelobes below -92 dB.
+    BlackmanHarris,
+    /// 4-term Nuttall window with a continuous first derivative, with sidelobes below
+    /// -93 dB.
+    Nuttall,
+    /// 5-term flat-top window, with a passband flat to within 0.01 dB for accurate
+    /// amplitude measurement of tones between bins.
+    FlatTop,
+}
+
+/// Endpoint convention of a window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Symmetry {
+    /// Symmetric about its center, with w(0) = w(N - 1), for filter design.
+    Symmetric,
+    /// One period of a window of length N + 1 with the last sample dropped, for
+    /// spectral analysis with a DFT of length N.
+    Periodic,
+}
+
+impl Window {
+    /// Retrieve the cosine-sum coefficients a<sub>0</sub>, a<sub>1</sub>, ... of the
+    /// window.
+    pub fn coefficients(&self) -> &'static [f64] {
+        match *self {
+            Window::Rectangular => &[1.0],
+            Window::Hann => &[0.5, 0.5],
+            Window::Hamming => &[0.54, 0.46],
+            Window::Blackman => &[0.42, 0.5, 0.08],
+            Window::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            Window::Nuttall => &[0.355768, 0.487396, 0.144232, 0.012604],
+            Window::FlatTop => &[0.21557895, 0.41663158, 0.277263158, 0.083578947,
+                                 0.006947368],
+        }
+    }
+
+    /// Generate the window of the given length with the given endpoint convention.
+    pub fn generate<T: Float>(&self, len: usize, symmetry: Symmetry) -> Vec<T> {
+        if len <= 1 {
+            return vec![T::one(); len];
+        }
+
+        let denom = match symmetry {
+            Symmetry::Symmetric => len - 1,
+            Symmetry::Periodic => len,
+        };
+
+        let coefs = self.coefficients();
+        let steps: Vec<f64> = (1..coefs.len())
+            .map(|k| 2.0 * PI * k as f64 / denom as f64)
+            .collect();
+        let mut bank = OscBank::new(&vec![0.0; steps.len()], &steps);
+
+        // Generate up to the center, w(0), ..., w(⌊D/2⌋).
+        let half = denom / 2 + 1;
+        let mut out = Vec::with_capacity(len);
+
+        for n in 0..half {
+            if n % SEGMENT == 0 && n > 0 {
+                for ch in 0..bank.len() {
+                    let k = ch + 1;
+                    bank.set_phase(ch, 2.0 * PI * (k * n % denom) as f64 / denom as f64);
+                }
+            }
+
+            let w = bank.cos().iter().zip(&coefs[1..]).enumerate()
+                .fold(coefs[0], |w, (k, (&cos, &a))| {
+                    if k % 2 == 0 { w - a * cos } else { w + a * cos }
+                });
+
+            out.push(T::from(w).unwrap());
+            bank.advance();
+        }
+
+        // Mirror w(n) = w(D - n) for the rest.
+        for n in half..len {
+            let w = out[denom - n];
+            out.push(w);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod test {
+    use super::*;
+
+    const ALL: [Window; 7] = [
+        Window::Rectangular,
+        Window::Hann,
+        Window::Hamming,
+        Window::Blackman,
+        Window::BlackmanHarris,
+        Window::Nuttall,
+        Window::FlatTop,
+    ];
+
+    /// Evaluate the reference formula of the given window at x = 2πn/D.
+    fn reference(window: Window, x: f64) -> f64 {
+        match window {
+            Window::Rectangular => 1.0,
+            Window::Hann => 0.5 * (1.0 - x.cos()),
+            Window::Hamming => 0.54 - 0.46 * x.cos(),
+            Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+            Window::BlackmanHarris =>
+                0.35875 - 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos() -
+                    0.01168 * (3.0 * x).cos(),
+            Window::Nuttall =>
+                0.355768 - 0.487396 * x.cos() + 0.144232 * (2.0 * x).cos() -
+                    0.012604 * (3.0 * x).cos(),
+            Window::FlatTop =>
+                0.21557895 - 0.41663158 * x.cos() + 0.277263158 * (2.0 * x).cos() -
+                    0.083578947 * (3.0 * x).cos() + 0.006947368 * (4.0 * x).cos(),
+        }
+    }
+
+    #[test]
+    fn test_reference() {
+        for &window in &ALL {
+            for &len in &[2, 3, 8, 9, 100, 101, 4096, 100_001] {
+                for &(sym, denom) in &[(Symmetry::Symmetric, len - 1),
+                                       (Symmetry::Periodic, len)] {
+                    let w: Vec<f64> = window.generate(len, sym);
+                    assert_eq!(w.len(), len);
+
+                    for (n, &w) in w.iter().enumerate() {
+                        let x = 2.0 * PI * n as f64 / denom as f64;
+                        assert!((w - reference(window, x)).abs() < 1.0e-12,
+                                "{:?} {} {:?} {}", window, len, sym, n);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shape() {
+        for &window in &ALL {
+            assert!(window.generate::<f64>(0, Symmetry::Symmetric).is_empty());
+            assert_eq!(window.generate::<f64>(1, Symmetry::Periodic), vec![1.0]);
+
+            // Symmetric windows are exactly symmetric and peak at the center.
+            let w: Vec<f32> = window.generate(255, Symmetry::Symmetric);
+
+            for n in 0..255 {
+                assert_eq!(w[n], w[254 - n]);
+            }
+
+            assert!((w[127] - 1.0).abs() < 1.0e-6, "{:?}", window);
+
+            // Periodic windows are symmetric about N/2.
+            let w: Vec<f64> = window.generate(256, Symmetry::Periodic);
+
+            for n in 1..256 {
+                assert_eq!(w[n], w[256 - n]);
+            }
+        }
+
+        let w: Vec<f64> = Window::Hann.generate(5, Symmetry::Symmetric);
+        assert_eq!(w[0], 0.0);
+        assert_eq!(w[4], 0.0);
+        assert!((w[1] - 0.5).abs() < 1.0e-15);
+    }
+}
@@ -0,0 +1,244 @@
+//! Frequency-translating FIR filter.
+//!
+//! A `XlatingFir` gives the same output as a `ddc::Ddc`, mixing a channel at a chosen
+//! carrier down to 0 and then filtering and decimating it, but without mixing every
+//! input sample. Since the output of the down-converter with low-pass taps h(k) at
+//! sample n is
+//!
+//! > y(n) = Σ h(k)x(n - k)e<sup>-jω(n - k)</sup>
+//! > = e<sup>-jωn</sup> Σ h(k)e<sup>jωk</sup>x(n - k),
+//!
+//! the filter instead runs the complex band-pass taps h(k)e<sup>jωk</sup> on the input
+//! directly, and only the decimated outputs are multiplied by the derotation phasor
+//! e<sup>-jωn</sup>. The band-pass taps are generated by an `IQOsc` stepping by ω, and
+//! the derotation by another stepping by -ωR, which runs on from one call to the next.
+//!
+//! This module requires the `std` feature.
+
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use ddc::lowpass_taps;
+use IQOsc;
+
+/// Decimating FIR filter with taps translated to a carrier frequency.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct XlatingFir<T: Float> {
+    /// Holds the low-pass prototype taps h(k).
+    proto: Vec<T>,
+    /// Holds the band-pass taps h(k)e<sup>jωk</sup> as (re, im) in reverse order.
+    taps: Vec<(T, T)>,
+    /// Holds the last L input samples twice over, so they're always contiguous.
+    history: Vec<(T, T)>,
+    /// Index of the oldest sample in `history`.
+    pos: usize,
+    /// Decimation factor R.
+    factor: usize,
+    /// Number of samples fed since the last output.
+    count: usize,
+    /// Generates e<sup>-jωn</sup> at each output sample n.
+    rot: IQOsc<T>,
+    /// Phase step ω of the carrier.
+    step: T,
+}
+
+impl<T: Float> XlatingFir<T> {
+    /// Create a new `XlatingFir` that shifts the carrier with the given phase step ω
+    /// (in radians per input sample) down to 0, then filters with the given low-pass
+    /// taps h(k) and keeps one of every given number R of filtered samples.
+    ///
+    /// Panics if there are no taps or the decimation factor is zero.
+    pub fn new(taps: &[T], factor: usize, step: T) -> Self {
+        assert!(!taps.is_empty(), "no taps");
+        assert!(factor > 0, "zero decimation factor");
+
+        let fac = T::from(factor).unwrap();
+
+        let mut fir = XlatingFir {
+            proto: taps.to_vec(),
+            taps: vec![],
+            history: vec![(T::zero(), T::zero()); 2 * taps.len()],
+            pos: 0,
+            factor,
+            count: 0,
+            // The first output is at sample R - 1.
+            rot: IQOsc::new(-step * (fac - T::one()), -step * fac),
+            step,
+        };
+
+        fir.translate();
+        fir
+    }
+
+    /// Create a new `XlatingFir` for the carrier with the given phase step ω (in
+    /// radians per input sample), decimating by the given factor R with a
+    /// Blackman-windowed sinc low-pass prototype of the given length.
+    ///
+    /// This matches a `ddc::Ddc` with a `Decimator::lowpass()` of the same factor and
+    /// length.
+    pub fn lowpass(factor: usize, len: usize, step: T) -> Self {
+        assert!(factor > 0, "zero decimation factor");
+
+        let taps: Vec<T> = lowpass_taps(0.5 / factor as f64, len).into_iter()
+            .map(|h| T::from(h).unwrap())
+            .collect();
+
+        Self::new(&taps, factor, step)
+    }
+
+    /// Retrieve the decimation factor R.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Change the carrier to the given phase step ω (in radians per input sample),
+    /// recomputing the band-pass taps.
+    ///
+    /// The derotation phase continues as if the mixing phase of a `ddc::Ddc` had
+    /// changed to the new step at the current sample, so once the filter history
+    /// holds only samples fed after the change, the outputs match again.
+    pub fn set_step(&mut self, step: T) {
+        // The mixing oscillator of a down-converter already holds the phase for the
+        // next sample, so only the R - c - 1 samples after it up to the next output
+        // change to the new step.
+        let (sin, cos) = self.rot.next();
+        let left = T::from(self.factor - self.count - 1).unwrap();
+        let phase = sin.atan2(cos) + (self.step - step) * left;
+
+        self.rot = IQOsc::new(phase, -step * T::from(self.factor).unwrap());
+        self.step = step;
+        self.translate();
+    }
+
+    /// Feed the given (sin, cos) input sample, returning the next output sample after
+    /// every R input samples.
+    pub fn feed(&mut self, sample: (T, T)) -> Option<(T, T)> {
+        let len = self.taps.len();
+
+        // Store the sample as (re, im).
+        let sample = (sample.1, sample.0);
+        self.history[self.pos] = sample;
+        self.history[self.pos + len] = sample;
+        self.pos = (self.pos + 1) % len;
+        self.count += 1;
+
+        if self.count < self.factor {
+            return None;
+        }
+
+        self.count = 0;
+
+        let window = &self.history[self.pos..self.pos + len];
+        let (re, im) = window.iter().zip(&self.taps)
+            .fold((T::zero(), T::zero()), |acc, (x, h)| {
+                (acc.0 + h.0 * x.0 - h.1 * x.1, acc.1 + h.0 * x.1 + h.1 * x.0)
+            });
+
+        // Multiply by the derotation phasor cos + j sin.
+        let (sin, cos) = self.rot.next();
+
+        Some((re * sin + im * cos, re * cos - im * sin))
+    }
+
+    /// Process the given block of (sin, cos) input samples, appending the output
+    /// samples to the given buffer, and return the number appended.
+    pub fn process(&mut self, input: &[(T, T)], out: &mut Vec<(T, T)>) -> usize {
+        let start = out.len();
+        out.extend(input.iter().filter_map(|&s| self.feed(s)));
+        out.len() - start
+    }
+
+    /// Compute the band-pass taps h(k)e<sup>jωk</sup> from the prototype.
+    fn translate(&mut self) {
+        let mut osc = IQOsc::new(T::zero(), self.step);
+
+        let mut taps: Vec<(T, T)> = self.proto.iter().map(|&h| {
+            let (sin, cos) = osc.next();
+            (h * cos, h * sin)
+        }).collect();
+
+        taps.reverse();
+        self.taps = taps;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+    use ddc::{Ddc, Decimator};
+    use testutil::tones;
+
+    /// Holds the (amplitude, phase, frequency) of each tone of the test signal.
+    const TONES: &[(f64, f64, f64)] = &[(1.0, 0.4, 0.213), (1.0, -1.0, -0.17)];
+
+    #[test]
+    fn test_ddc() {
+        // Match mixing every sample before filtering.
+        let x = tones(TONES, 20_000);
+        let step = 2.0 * PI * 0.2;
+
+        let mut ddc = Ddc::new(step, Decimator::lowpass(10, 201));
+        let mut fir = XlatingFir::lowpass(10, 201, step);
+        assert_eq!(fir.factor(), 10);
+
+        let mut expected = vec![];
+        let mut out = vec![];
+        ddc.process(&x, &mut expected);
+        assert_eq!(fir.process(&x, &mut out), 2000);
+
+        for (a, b) in out.iter().zip(&expected) {
+            assert!((a.0 - b.0).abs() < 1.0e-12 && (a.1 - b.1).abs() < 1.0e-12);
+        }
+
+        // The tone at 0.213 ends up at 0.13 cycles per output sample, and the other
+        // is rejected.
+        for w in out[50..].windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let phase = (b.0 * a.1 - b.1 * a.0).atan2(b.1 * a.1 + b.0 * a.0);
+
+            assert!(((a.0 * a.0 + a.1 * a.1).sqrt() - 1.0).abs() < 1.0e-3);
+            assert!((phase - 2.0 * PI * 0.13).abs() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn test_retune() {
+        // Retuning and processing in blocks still matches the down-converter.
+        let x = tones(TONES, 9000);
+        let taps = [0.1f64, 0.2, 0.4, 0.2, 0.1];
+
+        let mut ddc = Ddc::new(1.3, Decimator::new(&taps, 3));
+        let mut fir = XlatingFir::new(&taps, 3, 1.3);
+
+        let mut expected = vec![];
+        let mut out = vec![];
+
+        // Retune at every phase of the decimation.
+        for (i, chunk) in x.chunks(1501).enumerate() {
+            ddc.process(chunk, &mut expected);
+            fir.process(chunk, &mut out);
+
+            let step = -0.5 + 0.3 * i as f64;
+            ddc.set_step(step);
+            fir.set_step(step);
+        }
+
+        assert_eq!(out.len(), 3000);
+
+        // Outputs that mix the old and new carriers differ, but once the filter
+        // history is all at the new carrier, they match again. Output n is at input
+        // sample 3n + 2.
+        for (n, (a, b)) in out.iter().zip(&expected).enumerate() {
+            let end = 3 * n + 2;
+
+            if end % 1501 >= 4 {
+                assert!((a.0 - b.0).abs() < 1.0e-9, "{}", n);
+                assert!((a.1 - b.1).abs() < 1.0e-9, "{}", n);
+            }
+        }
+    }
+}